@@ -0,0 +1,73 @@
+use ore_pool_api::state::Pool;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use steel::AccountDeserialize;
+
+use crate::error::Error;
+
+/// nominates a new authority for the pool.
+/// run with the current authority keypair and the new authority as the pubkey.
+pub async fn propose_authority(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    pool_authority: Pubkey,
+    new_authority: Result<Pubkey, Error>,
+) -> Result<(), Error> {
+    let new_authority = new_authority?;
+    let (pool_address, _) = ore_pool_api::state::pool_pda(pool_authority);
+    println!("pool address: {:?}", pool_address);
+    let cu_budget = ComputeBudgetInstruction::set_compute_unit_limit(50_000);
+    let cu_price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
+    let propose_ix =
+        ore_pool_api::sdk::propose_authority(keypair.pubkey(), pool_address, new_authority);
+    let mut tx =
+        Transaction::new_with_payer(&[cu_budget, cu_price, propose_ix], Some(&keypair.pubkey()));
+    let hash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[keypair], hash);
+    let sig = rpc_client.send_transaction(&tx).await?;
+    println!("OK: {:?}", sig);
+    Ok(())
+}
+
+/// accepts a pending transfer of the pool.
+/// run with the new authority keypair and the original pool authority.
+/// also creates a member account for the new authority,
+/// because this is where the operator commissions will be attributed.
+pub async fn accept_authority(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    pool_authority: Pubkey,
+) -> Result<(), Error> {
+    // assert transfer is pending
+    let (pool_address, _) = ore_pool_api::state::pool_pda(pool_authority);
+    println!("pool address: {:?}", pool_address);
+    let pool_data = rpc_client.get_account_data(&pool_address).await?;
+    let pool = Pool::try_from_bytes(pool_data.as_slice())?;
+    if pool.pending_authority.ne(&keypair.pubkey()) {
+        return Err(Error::NoPendingAuthority);
+    }
+
+    // accept transfer and get or create member account
+    let cu_budget = ComputeBudgetInstruction::set_compute_unit_limit(50_000);
+    let cu_price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
+    let accept_ix = ore_pool_api::sdk::accept_authority(keypair.pubkey(), pool_address);
+    let mut ixs = vec![cu_budget, cu_price, accept_ix];
+    let (member_address, _) = ore_pool_api::state::member_pda(keypair.pubkey(), pool_address);
+    println!("member address: {:?}", member_address);
+    if rpc_client.get_account_data(&member_address).await.is_err() {
+        ixs.push(ore_pool_api::sdk::join(
+            keypair.pubkey(),
+            pool_address,
+            keypair.pubkey(),
+        ));
+    }
+    let mut tx = Transaction::new_with_payer(ixs.as_slice(), Some(&keypair.pubkey()));
+    let hash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[keypair], hash);
+    let sig = rpc_client.send_transaction(&tx).await?;
+    println!("OK: {:?}", sig);
+    Ok(())
+}
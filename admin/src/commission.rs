@@ -0,0 +1,41 @@
+use ore_pool_api::state::Pool;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use steel::AccountDeserialize;
+
+use crate::error::Error;
+
+/// changes the operator commission.
+/// decreases apply immediately, while increases only apply once the on-chain timelock elapses.
+pub async fn update_commission(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    pool_authority: Pubkey,
+    commission: Result<u64, Error>,
+) -> Result<(), Error> {
+    // parse arguments
+    let commission = commission?;
+
+    // assert pool exists
+    let (pool_address, _) = ore_pool_api::state::pool_pda(pool_authority);
+    let pool_data = rpc_client.get_account_data(&pool_address).await?;
+    let pool = Pool::try_from_bytes(pool_data.as_slice())?;
+    println!("pool address: {:?}", pool_address);
+    println!("current commission: {}", pool.commission);
+
+    // submit update
+    let cu_budget = ComputeBudgetInstruction::set_compute_unit_limit(50_000);
+    let cu_price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
+    let update_ix =
+        ore_pool_api::sdk::update_commission(keypair.pubkey(), pool_address, commission);
+    let mut tx =
+        Transaction::new_with_payer(&[cu_budget, cu_price, update_ix], Some(&keypair.pubkey()));
+    let hash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[keypair], hash);
+    let sig = rpc_client.send_transaction(&tx).await?;
+    println!("OK: {:?}", sig);
+    Ok(())
+}
@@ -14,6 +14,8 @@ pub enum Error {
     SolanaClient(#[from] solana_client::client_error::ClientError),
     #[error("solana program")]
     SolaanProgram(#[from] solana_program::program_error::ProgramError),
+    #[error("std parse int")]
+    StdParseInt(#[from] std::num::ParseIntError),
     #[error("solana parse pubkey")]
     SolanaParsePubkey(#[from] solana_sdk::pubkey::ParsePubkeyError),
     #[error("missing pool url")]
@@ -22,4 +24,6 @@ pub enum Error {
     InvalidCommand,
     #[error("member pool mismatch")]
     MemberPoolMismatch,
+    #[error("keypair is not the pending pool authority")]
+    NoPendingAuthority,
 }
@@ -10,9 +10,11 @@ pub async fn init(
     rpc_client: &RpcClient,
     keypair: &Keypair,
     pool_url: Option<String>,
+    commission: Result<u64, Error>,
 ) -> Result<(), Error> {
     // parse arguments
     let pool_url = pool_url.ok_or(Error::MissingPoolUrl)?;
+    let commission = commission?;
     let pool_authority = keypair.pubkey();
 
     // Submit create if pool or reservation account does not exist
@@ -22,7 +24,8 @@ pub async fn init(
     if pool_data.is_err() {
         let cu_budget = ComputeBudgetInstruction::set_compute_unit_limit(1_000_000);
         let cu_price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
-        let launch_ix = ore_pool_api::sdk::launch(pool_authority, pool_authority, pool_url)?;
+        let launch_ix =
+            ore_pool_api::sdk::launch(pool_authority, pool_authority, pool_url, commission)?;
         let mut tx =
             Transaction::new_with_payer(&[cu_budget, cu_price, launch_ix], Some(&keypair.pubkey()));
         let hash = rpc_client.get_latest_blockhash().await?;
@@ -2,32 +2,57 @@ use std::str::FromStr;
 
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair, signer::EncodableKey};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::Keypair,
+    signer::{EncodableKey, Signer},
+};
 
+mod authority;
+mod commission;
 mod error;
 mod init;
 mod member_account;
+mod migrate_pool;
 mod pool_account;
 mod proof_account;
+mod update_url;
 
 #[tokio::main]
 async fn main() -> Result<(), error::Error> {
     // parse resources
     let command = command()?;
     let keypair = keypair()?;
+    let pool_authority = pool_authority(&keypair)?;
     let rpc_client = rpc_client()?;
     let pool_url = pool_url();
     let pubkey = pubkey();
+    let commission = commission();
     // run
     match command.as_str() {
-        "init" => init::init(&rpc_client, &keypair, pool_url).await,
-        "pool-account" => pool_account::pool_account(&rpc_client, &keypair).await,
-        "proof-account" => proof_account::proof_account(&rpc_client, &keypair).await,
-        "member-account" => member_account::member_account(&rpc_client, &keypair).await,
+        "init" => init::init(&rpc_client, &keypair, pool_url, commission).await,
+        "pool-account" => pool_account::pool_account(&rpc_client, pool_authority).await,
+        "proof-account" => proof_account::proof_account(&rpc_client, pool_authority).await,
+        "member-account" => {
+            member_account::member_account(&rpc_client, &keypair, pool_authority).await
+        }
         "member-account-lookup" => {
-            member_account::member_account_lookup(&rpc_client, &keypair, pubkey).await
+            member_account::member_account_lookup(&rpc_client, pool_authority, pubkey).await
         }
         "member-account-gpa" => member_account::member_account_gpa(&rpc_client, pubkey).await,
+        "update-url" => {
+            update_url::update_url(&rpc_client, &keypair, pool_authority, pool_url).await
+        }
+        "migrate-pool" => migrate_pool::migrate_pool(&rpc_client, &keypair, pool_authority).await,
+        "update-commission" => {
+            commission::update_commission(&rpc_client, &keypair, pool_authority, commission).await
+        }
+        "propose-authority" => {
+            authority::propose_authority(&rpc_client, &keypair, pool_authority, pubkey).await
+        }
+        "accept-authority" => {
+            authority::accept_authority(&rpc_client, &keypair, pool_authority).await
+        }
         _ => Err(error::Error::InvalidCommand),
     }
 }
@@ -53,6 +78,22 @@ fn pool_url() -> Option<String> {
     std::env::var("POOL_URL").ok()
 }
 
+/// The authority the pool was launched with.
+/// Defaults to the keypair if the pool authority was never transferred.
+fn pool_authority(keypair: &Keypair) -> Result<Pubkey, error::Error> {
+    match std::env::var("POOL_AUTHORITY") {
+        Ok(pubkey_str) => Pubkey::from_str(pubkey_str.as_str()).map_err(From::from),
+        Err(_) => Ok(keypair.pubkey()),
+    }
+}
+
+/// The operator commission in % percentage.
+fn commission() -> Result<u64, error::Error> {
+    let commission_str = std::env::var("COMMISSION")?;
+    let commission = commission_str.parse()?;
+    Ok(commission)
+}
+
 fn pubkey() -> Result<Pubkey, error::Error> {
     let pubkey_str = std::env::var("PUBKEY")?;
     let pubkey = Pubkey::from_str(pubkey_str.as_str())?;
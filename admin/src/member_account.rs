@@ -10,8 +10,12 @@ use crate::error::Error;
 /// because this is where the operator commissions will be attributed.
 /// this command will fetch and print the address and decoded data of the member account.
 /// to manage this account (claim, stake, etc), use the ore-cli.
-pub async fn member_account(rpc_client: &RpcClient, keypair: &Keypair) -> Result<(), Error> {
-    let (pool_pda, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
+pub async fn member_account(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    pool_authority: Pubkey,
+) -> Result<(), Error> {
+    let (pool_pda, _) = ore_pool_api::state::pool_pda(pool_authority);
     let (member_pda, _) = ore_pool_api::state::member_pda(keypair.pubkey(), pool_pda);
     println!("member address: {:?}", member_pda);
     let data = rpc_client.get_account_data(&member_pda).await?;
@@ -22,11 +26,11 @@ pub async fn member_account(rpc_client: &RpcClient, keypair: &Keypair) -> Result
 
 pub async fn member_account_lookup(
     rpc_client: &RpcClient,
-    keypair: &Keypair,
+    pool_authority: Pubkey,
     pubkey: Result<Pubkey, Error>,
 ) -> Result<(), Error> {
     let pubkey = pubkey?;
-    let (pool_pda, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
+    let (pool_pda, _) = ore_pool_api::state::pool_pda(pool_authority);
     let (member_pda, _) = ore_pool_api::state::member_pda(pubkey, pool_pda);
     println!("member address: {:?}", member_pda);
     let data = rpc_client.get_account_data(&member_pda).await?;
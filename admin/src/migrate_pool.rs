@@ -0,0 +1,30 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+use crate::error::Error;
+
+/// reallocates the pool account to the latest layout.
+/// the keypair pays for any additional rent.
+/// pools that predate on-chain commissions start at zero,
+/// set the commission with the update-commission command after.
+pub async fn migrate_pool(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    pool_authority: Pubkey,
+) -> Result<(), Error> {
+    let (pool_address, _) = ore_pool_api::state::pool_pda(pool_authority);
+    println!("pool address: {:?}", pool_address);
+    let cu_budget = ComputeBudgetInstruction::set_compute_unit_limit(50_000);
+    let cu_price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
+    let migrate_ix = ore_pool_api::sdk::migrate_pool(keypair.pubkey(), pool_address);
+    let mut tx =
+        Transaction::new_with_payer(&[cu_budget, cu_price, migrate_ix], Some(&keypair.pubkey()));
+    let hash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[keypair], hash);
+    let sig = rpc_client.send_transaction(&tx).await?;
+    println!("OK: {:?}", sig);
+    Ok(())
+}
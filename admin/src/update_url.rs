@@ -0,0 +1,39 @@
+use ore_pool_api::state::Pool;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{signature::Keypair, signer::Signer};
+use steel::AccountDeserialize;
+
+use crate::error::Error;
+
+/// rewrites the url where members should submit hashes.
+/// use this after moving the pool server to a new host.
+pub async fn update_url(
+    rpc_client: &RpcClient,
+    keypair: &Keypair,
+    pool_authority: Pubkey,
+    pool_url: Option<String>,
+) -> Result<(), Error> {
+    // parse arguments
+    let pool_url = pool_url.ok_or(Error::MissingPoolUrl)?;
+
+    // assert pool exists
+    let (pool_address, _) = ore_pool_api::state::pool_pda(pool_authority);
+    let pool_data = rpc_client.get_account_data(&pool_address).await?;
+    Pool::try_from_bytes(pool_data.as_slice())?;
+    println!("pool address: {:?}", pool_address);
+
+    // submit update
+    let cu_budget = ComputeBudgetInstruction::set_compute_unit_limit(50_000);
+    let cu_price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
+    let update_ix = ore_pool_api::sdk::update_pool(keypair.pubkey(), pool_address, pool_url)?;
+    let mut tx =
+        Transaction::new_with_payer(&[cu_budget, cu_price, update_ix], Some(&keypair.pubkey()));
+    let hash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[keypair], hash);
+    let sig = rpc_client.send_transaction(&tx).await?;
+    println!("OK: {:?}", sig);
+    Ok(())
+}
@@ -12,6 +12,13 @@ pub const SHARE: &[u8] = b"share";
 /// The seed of the migration account PDA.
 pub const MIGRATION: &[u8] = b"migration";
 
+/// The maximum operator commission in % percentage.
+pub const MAX_COMMISSION: u64 = 100;
+
+/// The delay before a commission increase takes effect, in seconds.
+/// Gives members time to leave the pool before paying the new rate.
+pub const COMMISSION_TIMELOCK: i64 = 7 * 24 * 60 * 60;
+
 /// The authority allowed to run migrations.
 pub const ADMIN_ADDRESS: Pubkey = pubkey!("HBUh9g46wk2X89CvaNN15UmsznP59rh6od1h8JwYAopk");
 
@@ -11,6 +11,12 @@ pub enum PoolError {
     WithdrawOnlyMode = 2,
     #[error("Cannot attribute more rewards than are currently claimable")]
     AttributionTooLarge = 3,
+    #[error("Member must claim their full balance before leaving")]
+    MemberBalanceNotZero = 4,
+    #[error("Commission cannot exceed the maximum")]
+    CommissionTooLarge = 5,
+    #[error("Balance proof does not match the pool balance root")]
+    InvalidBalanceProof = 6,
 }
 
 #[derive(Debug, Error)]
@@ -1,5 +1,17 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
 use steel::*;
 
+#[repr(u64)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive)]
+pub enum PoolEventType {
+    Join = 0,
+    Attribute = 1,
+    Claim = 2,
+    Submit = 3,
+    Leave = 4,
+    TransferMember = 5,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
 pub struct UnstakeEvent {
@@ -13,4 +25,328 @@ pub struct UnstakeEvent {
     pub balance: u64,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct JoinEvent {
+    /// the event type discriminator
+    pub disc: u64,
+    /// the authority of the member account
+    pub authority: Pubkey,
+    /// the member account
+    pub member: Pubkey,
+    /// the pool account
+    pub pool: Pubkey,
+    /// the id assigned to the member
+    pub id: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct AttributeEvent {
+    /// the event type discriminator
+    pub disc: u64,
+    /// the authority of the member account
+    pub authority: Pubkey,
+    /// the member account
+    pub member: Pubkey,
+    /// the pool account
+    pub pool: Pubkey,
+    /// amount added to the claimable balance
+    pub balance_change: u64,
+    /// latest claimable balance
+    pub balance: u64,
+    /// latest lifetime balance
+    pub total_balance: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct ClaimEvent {
+    /// the event type discriminator
+    pub disc: u64,
+    /// the authority of the member account
+    pub authority: Pubkey,
+    /// the member account
+    pub member: Pubkey,
+    /// the token account receiving the claim
+    pub beneficiary: Pubkey,
+    /// amount claimed
+    pub amount: u64,
+    /// latest claimable balance
+    pub balance: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct SubmitEvent {
+    /// the event type discriminator
+    pub disc: u64,
+    /// the authority that submitted the hash
+    pub authority: Pubkey,
+    /// the pool account
+    pub pool: Pubkey,
+    /// the attestation of the pool's hash-power
+    pub attestation: [u8; 32],
+    /// the last hash at of the proof after submission
+    pub last_hash_at: i64,
+    /// the total number of hashes submitted by the pool
+    pub total_submissions: u64,
+    /// the total number of members at submission
+    pub total_members: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct LeaveEvent {
+    /// the event type discriminator
+    pub disc: u64,
+    /// the authority of the member account
+    pub authority: Pubkey,
+    /// the closed member account
+    pub member: Pubkey,
+    /// the pool account
+    pub pool: Pubkey,
+    /// the id of the member, which is not reused
+    pub id: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct TransferMemberEvent {
+    /// the event type discriminator
+    pub disc: u64,
+    /// the authority the member account was transferred from
+    pub authority: Pubkey,
+    /// the authority the member account was transferred to
+    pub new_authority: Pubkey,
+    /// the closed member account
+    pub member: Pubkey,
+    /// the member account of the new authority
+    pub new_member: Pubkey,
+    /// the pool account
+    pub pool: Pubkey,
+    /// the id of the member, carried to the new account
+    pub id: u64,
+    /// claimable balance carried to the new account
+    pub balance: u64,
+    /// lifetime balance carried to the new account
+    pub total_balance: u64,
+}
+
 event!(UnstakeEvent);
+event!(JoinEvent);
+event!(AttributeEvent);
+event!(ClaimEvent);
+event!(SubmitEvent);
+event!(LeaveEvent);
+event!(TransferMemberEvent);
+
+/// An event logged by the pool program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoolEvent {
+    Join(JoinEvent),
+    Attribute(AttributeEvent),
+    Claim(ClaimEvent),
+    Submit(SubmitEvent),
+    Leave(LeaveEvent),
+    TransferMember(TransferMemberEvent),
+    Unstake(UnstakeEvent),
+}
+
+impl PoolEvent {
+    /// Decodes an event from the bytes of a single log data entry.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        // The full discriminator picks the event, which must then match its size exactly
+        let disc = data
+            .get(..8)
+            .and_then(|disc| disc.try_into().ok())
+            .map(u64::from_le_bytes)
+            .and_then(|disc| PoolEventType::try_from(disc).ok());
+        let event = disc.and_then(|disc| match disc {
+            PoolEventType::Join => bytemuck::try_pod_read_unaligned(data).ok().map(Self::Join),
+            PoolEventType::Attribute => bytemuck::try_pod_read_unaligned(data)
+                .ok()
+                .map(Self::Attribute),
+            PoolEventType::Claim => bytemuck::try_pod_read_unaligned(data).ok().map(Self::Claim),
+            PoolEventType::Submit => bytemuck::try_pod_read_unaligned(data)
+                .ok()
+                .map(Self::Submit),
+            PoolEventType::Leave => bytemuck::try_pod_read_unaligned(data).ok().map(Self::Leave),
+            PoolEventType::TransferMember => bytemuck::try_pod_read_unaligned(data)
+                .ok()
+                .map(Self::TransferMember),
+        });
+
+        // Legacy events are not prefixed with a discriminator
+        event.or_else(|| {
+            bytemuck::try_pod_read_unaligned(data)
+                .ok()
+                .map(Self::Unstake)
+        })
+    }
+}
+
+/// Decodes the events logged by the pool program from a transaction's log messages.
+///
+/// Log data is only decoded while the pool program is at the top of the invocation stack,
+/// so data logged by other programs in the same transaction is ignored.
+pub fn parse_events(log_messages: &[String]) -> Vec<PoolEvent> {
+    let program_id = crate::ID.to_string();
+    let mut stack: Vec<&str> = vec![];
+    let mut events = vec![];
+    for log_message in log_messages.iter() {
+        if let Some(data) = log_message.strip_prefix("Program data: ") {
+            if stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            for data in data.split_whitespace() {
+                let Ok(bytes) = BASE64_STANDARD.decode(data) else {
+                    continue;
+                };
+                if let Some(event) = PoolEvent::from_bytes(bytes.as_slice()) {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+        let words: Vec<&str> = log_message.split_whitespace().collect();
+        match words.as_slice() {
+            ["Program", id, "invoke", ..] => stack.push(id),
+            ["Program", _, "success"] | ["Program", _, "failed:", ..] => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_logs(program_id: &Pubkey, data: &[Vec<u8>]) -> Vec<String> {
+        let mut logs = vec![format!("Program {} invoke [1]", program_id)];
+        for data in data.iter() {
+            logs.push(format!("Program data: {}", BASE64_STANDARD.encode(data)));
+        }
+        logs.push(format!("Program {} success", program_id));
+        logs
+    }
+
+    fn join_event() -> JoinEvent {
+        JoinEvent {
+            disc: PoolEventType::Join as u64,
+            authority: Pubkey::new_unique(),
+            member: Pubkey::new_unique(),
+            pool: Pubkey::new_unique(),
+            id: 7,
+        }
+    }
+
+    fn leave_event() -> LeaveEvent {
+        LeaveEvent {
+            disc: PoolEventType::Leave as u64,
+            authority: Pubkey::new_unique(),
+            member: Pubkey::new_unique(),
+            pool: Pubkey::new_unique(),
+            id: 7,
+        }
+    }
+
+    #[test]
+    fn parse_events_round_trip() {
+        let events = vec![
+            PoolEvent::Join(join_event()),
+            PoolEvent::Attribute(AttributeEvent {
+                disc: PoolEventType::Attribute as u64,
+                authority: Pubkey::new_unique(),
+                member: Pubkey::new_unique(),
+                pool: Pubkey::new_unique(),
+                balance_change: 10,
+                balance: 20,
+                total_balance: 30,
+            }),
+            PoolEvent::Claim(ClaimEvent {
+                disc: PoolEventType::Claim as u64,
+                authority: Pubkey::new_unique(),
+                member: Pubkey::new_unique(),
+                beneficiary: Pubkey::new_unique(),
+                amount: 10,
+                balance: 10,
+            }),
+            PoolEvent::Submit(SubmitEvent {
+                disc: PoolEventType::Submit as u64,
+                authority: Pubkey::new_unique(),
+                pool: Pubkey::new_unique(),
+                attestation: [1; 32],
+                last_hash_at: 100,
+                total_submissions: 2,
+                total_members: 3,
+            }),
+            PoolEvent::Leave(leave_event()),
+            PoolEvent::TransferMember(TransferMemberEvent {
+                disc: PoolEventType::TransferMember as u64,
+                authority: Pubkey::new_unique(),
+                new_authority: Pubkey::new_unique(),
+                member: Pubkey::new_unique(),
+                new_member: Pubkey::new_unique(),
+                pool: Pubkey::new_unique(),
+                id: 7,
+                balance: 10,
+                total_balance: 30,
+            }),
+            PoolEvent::Unstake(UnstakeEvent {
+                authority: Pubkey::new_unique(),
+                share: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                balance: 10,
+            }),
+        ];
+        let data: Vec<Vec<u8>> = events
+            .iter()
+            .map(|event| match event {
+                PoolEvent::Join(event) => event.to_bytes().to_vec(),
+                PoolEvent::Attribute(event) => event.to_bytes().to_vec(),
+                PoolEvent::Claim(event) => event.to_bytes().to_vec(),
+                PoolEvent::Submit(event) => event.to_bytes().to_vec(),
+                PoolEvent::Leave(event) => event.to_bytes().to_vec(),
+                PoolEvent::TransferMember(event) => event.to_bytes().to_vec(),
+                PoolEvent::Unstake(event) => event.to_bytes().to_vec(),
+            })
+            .collect();
+        assert_eq!(parse_events(&program_logs(&crate::ID, &data)), events);
+    }
+
+    #[test]
+    fn parse_events_tells_apart_events_of_the_same_size() {
+        assert_eq!(
+            std::mem::size_of::<JoinEvent>(),
+            std::mem::size_of::<LeaveEvent>()
+        );
+        let join = join_event();
+        let leave = leave_event();
+        let data = vec![join.to_bytes().to_vec(), leave.to_bytes().to_vec()];
+        assert_eq!(
+            parse_events(&program_logs(&crate::ID, &data)),
+            vec![PoolEvent::Join(join), PoolEvent::Leave(leave)]
+        );
+    }
+
+    #[test]
+    fn parse_events_ignores_other_programs() {
+        let data = vec![join_event().to_bytes().to_vec()];
+        let mut logs = program_logs(&Pubkey::new_unique(), &data);
+        logs.extend(program_logs(&crate::ID, &[]));
+        assert!(parse_events(&logs).is_empty());
+    }
+
+    #[test]
+    fn parse_events_skips_unknown_data() {
+        // only the first byte of the discriminator matches a known event
+        let mut unknown = join_event();
+        unknown.disc = (1 << 8) | PoolEventType::Join as u64;
+        let data = vec![unknown.to_bytes().to_vec(), vec![0; 3]];
+        assert!(parse_events(&program_logs(&crate::ID, &data)).is_empty());
+    }
+}
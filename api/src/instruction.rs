@@ -17,6 +17,9 @@ pub enum PoolInstruction {
     )]
     Stake = 3,
     Unstake = 4,
+    TransferMember = 5,
+    Leave = 6,
+    ClaimWithProof = 7,
 
     // Operator
     Attribute = 100,
@@ -32,6 +35,12 @@ pub enum PoolInstruction {
     )]
     OpenStake = 103,
     Submit = 104,
+    AttributeMany = 105,
+    UpdatePool = 106,
+    ProposeAuthority = 107,
+    AcceptAuthority = 108,
+    UpdateCommission = 109,
+    UpdateBalanceRoot = 110,
 
     // Migration
     MigratePool = 200,
@@ -39,12 +48,22 @@ pub enum PoolInstruction {
     QuickMigrate = 202,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct AcceptAuthority {}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct Attribute {
     pub total_balance: [u8; 8],
 }
 
+/// The total balances of each member, as little-endian u64s, follow this header in the
+/// instruction data in the same order as the member accounts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct AttributeMany {}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct Claim {
@@ -53,6 +72,14 @@ pub struct Claim {
     pub pool_bump: u8,
 }
 
+/// The proof of the total balance, as 32 byte nodes, follows this header in the instruction data.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ClaimWithProof {
+    pub amount: [u8; 8],
+    pub total_balance: [u8; 8],
+}
+
 #[deprecated(
     since = "0.3.0",
     note = "Staking has moved to the global boost program"
@@ -69,8 +96,17 @@ pub struct Launch {
     #[deprecated(since = "0.1.3", note = "Bumps are no longer required")]
     pub proof_bump: u8,
     pub url: [u8; 128],
+    pub commission: [u8; 8],
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct Leave {}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct MigratePool {}
+
 #[deprecated(
     since = "0.3.0",
     note = "Staking has moved to the global boost program"
@@ -96,6 +132,12 @@ pub struct Join {
     pub member_bump: u8,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ProposeAuthority {
+    pub new_authority: Pubkey,
+}
+
 #[deprecated(
     since = "0.3.0",
     note = "Staking has moved to the global boost program"
@@ -114,19 +156,51 @@ pub struct Submit {
     pub nonce: [u8; 8],
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct UpdateBalanceRoot {
+    pub root: [u8; 32],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct UpdateCommission {
+    pub commission: [u8; 8],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct UpdatePool {
+    pub url: [u8; 128],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct TransferMember {}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct Unstake {
     pub amount: [u8; 8],
 }
 
+instruction!(PoolInstruction, AcceptAuthority);
 instruction!(PoolInstruction, Attribute);
+instruction!(PoolInstruction, AttributeMany);
 instruction!(PoolInstruction, Claim);
+instruction!(PoolInstruction, ClaimWithProof);
 instruction!(PoolInstruction, Commit);
 instruction!(PoolInstruction, Launch);
+instruction!(PoolInstruction, Leave);
+instruction!(PoolInstruction, MigratePool);
 instruction!(PoolInstruction, OpenShare);
 instruction!(PoolInstruction, OpenStake);
 instruction!(PoolInstruction, Join);
+instruction!(PoolInstruction, ProposeAuthority);
 instruction!(PoolInstruction, Stake);
 instruction!(PoolInstruction, Submit);
+instruction!(PoolInstruction, TransferMember);
 instruction!(PoolInstruction, Unstake);
+instruction!(PoolInstruction, UpdateBalanceRoot);
+instruction!(PoolInstruction, UpdateCommission);
+instruction!(PoolInstruction, UpdatePool);
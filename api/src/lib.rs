@@ -4,6 +4,7 @@ pub mod event;
 #[allow(deprecated)]
 pub mod instruction;
 pub mod loaders;
+pub mod merkle;
 pub mod sdk;
 pub mod state;
 
@@ -13,6 +14,7 @@ pub mod prelude {
     pub use crate::event::*;
     pub use crate::instruction::*;
     pub use crate::loaders::*;
+    pub use crate::merkle::*;
     pub use crate::sdk::*;
     pub use crate::state::*;
 }
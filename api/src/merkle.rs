@@ -0,0 +1,236 @@
+use solana_program::keccak::hashv;
+use steel::*;
+
+/// Domain separation between leaves and internal nodes,
+/// so an internal node can never be passed off as a leaf.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// Hashes the fields of a leaf.
+pub fn hash_leaf(fields: &[&[u8]]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(fields.len() + 1);
+    data.push(LEAF_PREFIX);
+    data.extend_from_slice(fields);
+    hashv(&data).to_bytes()
+}
+
+/// Hashes a pair of sibling nodes.
+/// Siblings are sorted, so proofs do not need to encode the position of each node.
+pub fn hash_node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Verifies that a leaf is included under the root.
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let node = proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_node(&node, sibling));
+    node.eq(root)
+}
+
+/// The leaf committing to a member's lifetime earnings in the pool balance root.
+/// Keyed by the member id as well as the authority, since a member that leaves and
+/// joins again gets a new id and starts over from zero.
+pub fn balance_leaf(authority: &Pubkey, id: u64, total_balance: u64) -> [u8; 32] {
+    hash_leaf(&[
+        authority.as_ref(),
+        &id.to_le_bytes(),
+        &total_balance.to_le_bytes(),
+    ])
+}
+
+/// The leaf committing to a member's contribution in the pool attestation.
+pub fn contribution_leaf(member: &Pubkey, digest: &[u8; 16], nonce: &[u8; 8]) -> [u8; 32] {
+    hash_leaf(&[member.as_ref(), digest, nonce])
+}
+
+/// Verifies that a member's contribution was counted in an attestation posted by the pool.
+pub fn verify_attestation(
+    attestation: &[u8; 32],
+    member: &Pubkey,
+    digest: &[u8; 16],
+    nonce: &[u8; 8],
+    proof: &[[u8; 32]],
+) -> bool {
+    verify(attestation, contribution_leaf(member, digest, nonce), proof)
+}
+
+/// A Merkle tree for building roots and proofs off chain.
+#[derive(Clone, Debug, Default)]
+pub struct MerkleTree {
+    /// The nodes of each layer, from the leaves up to the root.
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+            // an odd node out is promoted to the next layer as is
+            let next = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_node(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        Self { layers }
+    }
+
+    /// The root of the tree (zeroed if there are no leaves).
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or([0; 32])
+    }
+
+    /// The sibling nodes from the leaf at the given index up to the root.
+    pub fn proof(&self, index: usize) -> Option<Vec<[u8; 32]>> {
+        let leaves = self.layers.first()?;
+        if index >= leaves.len() {
+            return None;
+        }
+        let mut proof = vec![];
+        let mut index = index;
+        for layer in self.layers.iter().take(self.layers.len() - 1) {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: u64) -> Vec<[u8; 32]> {
+        (0..n)
+            .map(|id| balance_leaf(&Pubkey::new_unique(), id, id * 100))
+            .collect()
+    }
+
+    #[test]
+    fn proofs_verify_every_leaf() {
+        for n in 1..=9 {
+            let leaves = leaves(n);
+            let tree = MerkleTree::new(leaves.clone());
+            let root = tree.root();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(verify(&root, *leaf, &proof), "leaf {} of {}", index, n);
+            }
+            assert!(tree.proof(leaves.len()).is_none());
+        }
+    }
+
+    #[test]
+    fn odd_leaves_are_promoted() {
+        let leaves = leaves(3);
+        let tree = MerkleTree::new(leaves.clone());
+        let root = hash_node(&hash_node(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(tree.root(), root);
+        assert_eq!(
+            tree.proof(2).unwrap(),
+            vec![hash_node(&leaves[0], &leaves[1])]
+        );
+    }
+
+    #[test]
+    fn siblings_are_sorted() {
+        let leaves = leaves(2);
+        assert_eq!(
+            hash_node(&leaves[0], &leaves[1]),
+            hash_node(&leaves[1], &leaves[0])
+        );
+        let reversed: Vec<_> = leaves.iter().rev().copied().collect();
+        assert_eq!(
+            MerkleTree::new(leaves).root(),
+            MerkleTree::new(reversed).root()
+        );
+    }
+
+    #[test]
+    fn single_and_empty_trees() {
+        let leaves = leaves(1);
+        let tree = MerkleTree::new(leaves.clone());
+        assert_eq!(tree.root(), leaves[0]);
+        assert!(tree.proof(0).unwrap().is_empty());
+        assert!(verify(&tree.root(), leaves[0], &[]));
+
+        let tree = MerkleTree::new(vec![]);
+        assert_eq!(tree.root(), [0; 32]);
+        assert!(tree.proof(0).is_none());
+    }
+
+    #[test]
+    fn verify_rejects_other_leaves_and_proofs() {
+        let leaves = leaves(4);
+        let tree = MerkleTree::new(leaves.clone());
+        let root = tree.root();
+        let proof = tree.proof(0).unwrap();
+        assert!(!verify(&root, leaves[1], &proof));
+        assert!(!verify(&root, leaves[0], &proof[..1]));
+        assert!(!verify(&[0; 32], leaves[0], &proof));
+
+        // leaf fields that read as a pair of nodes do not hash to their parent
+        let node = hash_node(&leaves[0], &leaves[1]);
+        assert_ne!(hash_leaf(&[&leaves[0], &leaves[1]]), node);
+    }
+
+    #[test]
+    fn balance_leaves_commit_to_the_member_id() {
+        let authority = Pubkey::new_unique();
+        assert_ne!(
+            balance_leaf(&authority, 0, 100),
+            balance_leaf(&authority, 1, 100)
+        );
+        assert_ne!(
+            balance_leaf(&authority, 0, 100),
+            balance_leaf(&authority, 0, 101)
+        );
+    }
+
+    #[test]
+    fn verify_attestation_checks_the_contribution() {
+        let member = Pubkey::new_unique();
+        let (digest, nonce) = ([1; 16], [2; 8]);
+        let leaves = vec![
+            contribution_leaf(&member, &digest, &nonce),
+            contribution_leaf(&Pubkey::new_unique(), &digest, &nonce),
+            contribution_leaf(&Pubkey::new_unique(), &[3; 16], &[4; 8]),
+        ];
+        let tree = MerkleTree::new(leaves);
+        let attestation = tree.root();
+        let proof = tree.proof(0).unwrap();
+        assert!(verify_attestation(
+            &attestation,
+            &member,
+            &digest,
+            &nonce,
+            &proof
+        ));
+        assert!(!verify_attestation(
+            &attestation,
+            &member,
+            &digest,
+            &[0; 8],
+            &proof
+        ));
+        assert!(!verify_attestation(
+            &attestation,
+            &Pubkey::new_unique(),
+            &digest,
+            &nonce,
+            &proof
+        ));
+    }
+}
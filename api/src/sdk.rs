@@ -14,7 +14,12 @@ use crate::{
 
 /// Builds a launch instruction.
 #[allow(deprecated)]
-pub fn launch(signer: Pubkey, miner: Pubkey, url: String) -> Result<Instruction, ApiError> {
+pub fn launch(
+    signer: Pubkey,
+    miner: Pubkey,
+    url: String,
+    commission: u64,
+) -> Result<Instruction, ApiError> {
     let url = url_to_bytes(url.as_str())?;
     let (pool_pda, pool_bump) = pool_pda(signer);
     let (proof_pda, proof_bump) = pool_proof_pda(pool_pda);
@@ -36,12 +41,91 @@ pub fn launch(signer: Pubkey, miner: Pubkey, url: String) -> Result<Instruction,
             pool_bump,
             proof_bump,
             url,
+            commission: commission.to_le_bytes(),
         }
         .to_bytes(),
     };
     Ok(ix)
 }
 
+/// Builds an update pool instruction.
+pub fn update_pool(signer: Pubkey, pool: Pubkey, url: String) -> Result<Instruction, ApiError> {
+    let url = url_to_bytes(url.as_str())?;
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(pool, false),
+        ],
+        data: UpdatePool { url }.to_bytes(),
+    };
+    Ok(ix)
+}
+
+/// Builds an update balance root instruction.
+pub fn update_balance_root(signer: Pubkey, pool: Pubkey, root: [u8; 32]) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(pool, false),
+        ],
+        data: UpdateBalanceRoot { root }.to_bytes(),
+    }
+}
+
+/// Builds an update commission instruction.
+pub fn update_commission(signer: Pubkey, pool: Pubkey, commission: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(pool, false),
+        ],
+        data: UpdateCommission {
+            commission: commission.to_le_bytes(),
+        }
+        .to_bytes(),
+    }
+}
+
+/// Builds a propose authority instruction.
+pub fn propose_authority(signer: Pubkey, pool: Pubkey, new_authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(pool, false),
+        ],
+        data: ProposeAuthority { new_authority }.to_bytes(),
+    }
+}
+
+/// Builds an accept authority instruction.
+pub fn accept_authority(signer: Pubkey, pool: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(pool, false),
+        ],
+        data: AcceptAuthority {}.to_bytes(),
+    }
+}
+
+/// Builds a migrate pool instruction.
+pub fn migrate_pool(signer: Pubkey, pool: Pubkey) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: MigratePool {}.to_bytes(),
+    }
+}
+
 /// Builds an join instruction.
 #[allow(deprecated)]
 pub fn join(member_authority: Pubkey, pool: Pubkey, payer: Pubkey) -> Instruction {
@@ -59,6 +143,38 @@ pub fn join(member_authority: Pubkey, pool: Pubkey, payer: Pubkey) -> Instructio
     }
 }
 
+/// Builds a leave instruction.
+pub fn leave(signer: Pubkey, pool: Pubkey) -> Instruction {
+    let (member_pda, _) = member_pda(signer, pool);
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(member_pda, false),
+            AccountMeta::new(pool, false),
+        ],
+        data: Leave {}.to_bytes(),
+    }
+}
+
+/// Builds a transfer member instruction.
+pub fn transfer_member(signer: Pubkey, pool: Pubkey, new_authority: Pubkey) -> Instruction {
+    let (old_member_pda, _) = member_pda(signer, pool);
+    let (new_member_pda, _) = member_pda(new_authority, pool);
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new_readonly(new_authority, false),
+            AccountMeta::new(old_member_pda, false),
+            AccountMeta::new(new_member_pda, false),
+            AccountMeta::new_readonly(pool, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: TransferMember {}.to_bytes(),
+    }
+}
+
 /// Builds a claim instruction.
 #[allow(deprecated)]
 pub fn claim(
@@ -93,9 +209,52 @@ pub fn claim(
     }
 }
 
+/// Builds a claim with proof instruction.
+pub fn claim_with_proof(
+    signer: Pubkey,
+    beneficiary: Pubkey,
+    pool_address: Pubkey,
+    amount: u64,
+    total_balance: u64,
+    proof: &[[u8; 32]],
+) -> Instruction {
+    let (member_pda, _) = member_pda(signer, pool_address);
+    let (pool_proof_pda, _) = proof_pda(pool_address);
+    let pool_tokens_address =
+        spl_associated_token_account::get_associated_token_address(&pool_address, &MINT_ADDRESS);
+    let mut data = ClaimWithProof {
+        amount: amount.to_le_bytes(),
+        total_balance: total_balance.to_le_bytes(),
+    }
+    .to_bytes();
+    for node in proof.iter() {
+        data.extend_from_slice(node);
+    }
+    Instruction {
+        program_id: crate::ID,
+        accounts: vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(beneficiary, false),
+            AccountMeta::new(member_pda, false),
+            AccountMeta::new(pool_address, false),
+            AccountMeta::new(pool_tokens_address, false),
+            AccountMeta::new(pool_proof_pda, false),
+            AccountMeta::new_readonly(TREASURY_ADDRESS, false),
+            AccountMeta::new(TREASURY_TOKENS_ADDRESS, false),
+            AccountMeta::new_readonly(ore_api::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+        ],
+        data,
+    }
+}
+
 /// Builds an attribute instruction.
-pub fn attribute(signer: Pubkey, member_authority: Pubkey, total_balance: u64) -> Instruction {
-    let (pool_address, _) = pool_pda(signer);
+pub fn attribute(
+    signer: Pubkey,
+    pool_address: Pubkey,
+    member_authority: Pubkey,
+    total_balance: u64,
+) -> Instruction {
     let (proof_address, _) = pool_proof_pda(pool_address);
     let (member_address, _) = member_pda(member_authority, pool_address);
     let pool_tokens_address =
@@ -116,6 +275,36 @@ pub fn attribute(signer: Pubkey, member_authority: Pubkey, total_balance: u64) -
     }
 }
 
+/// Builds an attribute many instruction.
+///
+/// Each entry is a member authority and the lifetime total balance to attribute to it.
+pub fn attribute_many(
+    signer: Pubkey,
+    pool_address: Pubkey,
+    members: &[(Pubkey, u64)],
+) -> Instruction {
+    let (proof_address, _) = pool_proof_pda(pool_address);
+    let pool_tokens_address =
+        spl_associated_token_account::get_associated_token_address(&pool_address, &MINT_ADDRESS);
+    let mut accounts = vec![
+        AccountMeta::new(signer, true),
+        AccountMeta::new(pool_address, false),
+        AccountMeta::new(pool_tokens_address, false),
+        AccountMeta::new(proof_address, false),
+    ];
+    let mut data = AttributeMany {}.to_bytes();
+    for (member_authority, total_balance) in members.iter() {
+        let (member_address, _) = member_pda(*member_authority, pool_address);
+        accounts.push(AccountMeta::new(member_address, false));
+        data.extend_from_slice(&total_balance.to_le_bytes());
+    }
+    Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    }
+}
+
 /// Builds a commit instruction.
 #[deprecated(
     since = "0.3.0",
@@ -129,20 +318,20 @@ pub fn commit(_signer: Pubkey, _mint: Pubkey) -> Instruction {
 /// Builds an submit instruction.
 pub fn submit(
     signer: Pubkey,
+    pool_address: Pubkey,
     solution: Solution,
     attestation: [u8; 32],
     bus: Pubkey,
     // boost_accounts: Option<[Pubkey; 3]>,
 ) -> Instruction {
-    let (pool_pda, _) = pool_pda(signer);
-    let (proof_pda, _) = pool_proof_pda(pool_pda);
+    let (proof_pda, _) = pool_proof_pda(pool_address);
     let (boost_config, _) = ore_boost_api::state::config_pda();
     let (boost_proof, _) = ore_api::state::proof_pda(boost_config);
     let accounts = vec![
         AccountMeta::new(signer, true),
         AccountMeta::new(bus, false),
         AccountMeta::new_readonly(CONFIG_ADDRESS, false),
-        AccountMeta::new(pool_pda, false),
+        AccountMeta::new(pool_address, false),
         AccountMeta::new(proof_pda, false),
         AccountMeta::new_readonly(ore_api::ID, false),
         AccountMeta::new_readonly(system_program::ID, false),
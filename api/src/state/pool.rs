@@ -6,7 +6,7 @@ use super::AccountDiscriminator;
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
 pub struct Pool {
-    /// The authority of this pool.
+    /// The authority allowed to operate this pool.
     pub authority: Pubkey,
 
     /// The bump used for signing CPIs.
@@ -33,6 +33,40 @@ pub struct Pool {
 
     /// The total number of members in this pool at the last submission.
     pub last_total_members: u64,
+
+    /// The authority this pool was launched with. Seeds the pool PDA for signing CPIs.
+    pub seed_authority: Pubkey,
+
+    /// The authority nominated to take over this pool (zeroed if none).
+    pub pending_authority: Pubkey,
+
+    /// The id assigned to the next member to join.
+    /// Ids are not reused when members leave, so this bounds the id space.
+    pub next_member_id: u64,
+
+    /// The operator commission in % percentage, taken from the pool's mining rewards.
+    pub commission: u64,
+
+    /// The commission scheduled to replace the current one.
+    pub pending_commission: u64,
+
+    /// The unix timestamp at which the pending commission takes effect (zero if none).
+    pub commission_effective_at: i64,
+
+    /// The Merkle root of every member's lifetime earnings (zeroed if not posted).
+    /// Members can claim against it without waiting for attribution.
+    pub balance_root: [u8; 32],
+}
+
+impl Pool {
+    /// The commission in effect at the given unix timestamp.
+    pub fn commission_at(&self, timestamp: i64) -> u64 {
+        if self.commission_effective_at > 0 && timestamp >= self.commission_effective_at {
+            self.pending_commission
+        } else {
+            self.commission
+        }
+    }
 }
 
 account!(AccountDiscriminator, Pool);
@@ -25,3 +25,227 @@ BEGIN
     END IF;
 END
 $$;
+
+-- create attestation leaves table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'attestation_leaves') THEN
+        CREATE TABLE attestation_leaves (
+          pool_address VARCHAR NOT NULL,
+          last_hash_at BIGINT NOT NULL,
+          leaf_index INT NOT NULL,
+          member VARCHAR NOT NULL,
+          digest BYTEA NOT NULL,
+          nonce BYTEA NOT NULL,
+          PRIMARY KEY (pool_address, last_hash_at, leaf_index)
+        );
+    END IF;
+END
+$$;
+
+-- create contributions table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'contributions') THEN
+        CREATE TABLE contributions (
+          id BIGSERIAL PRIMARY KEY,
+          pool_address VARCHAR NOT NULL,
+          member VARCHAR NOT NULL,
+          last_hash_at BIGINT NOT NULL,
+          digest BYTEA NOT NULL,
+          nonce BYTEA NOT NULL,
+          difficulty INT NOT NULL,
+          score BIGINT NOT NULL,
+          status VARCHAR NOT NULL
+        );
+    END IF;
+END
+$$;
+
+-- create indexes on contributions member and last hash at
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_indexes WHERE indexname = 'contributions_member_idx') THEN
+        CREATE INDEX contributions_member_idx ON contributions(pool_address, member, last_hash_at);
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_indexes WHERE indexname = 'contributions_last_hash_at_idx') THEN
+        CREATE INDEX contributions_last_hash_at_idx ON contributions(pool_address, last_hash_at);
+    END IF;
+END
+$$;
+
+-- create aggregator snapshots table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'aggregator_snapshots') THEN
+        CREATE TABLE aggregator_snapshots (
+          pool_address VARCHAR PRIMARY KEY,
+          data BYTEA NOT NULL,
+          updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        );
+    END IF;
+END
+$$;
+
+-- create reward policy states table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'reward_policy_states') THEN
+        CREATE TABLE reward_policy_states (
+          pool_address VARCHAR NOT NULL,
+          policy VARCHAR NOT NULL,
+          data BYTEA NOT NULL,
+          updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+          PRIMARY KEY (pool_address, policy)
+        );
+    END IF;
+END
+$$;
+
+-- create mining events table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'mining_events') THEN
+        CREATE TABLE mining_events (
+          pool_address VARCHAR NOT NULL,
+          last_hash_at BIGINT NOT NULL,
+          signature VARCHAR NOT NULL,
+          block BIGINT NOT NULL,
+          timestamp BIGINT NOT NULL,
+          balance BIGINT NOT NULL,
+          difficulty BIGINT NOT NULL,
+          timing BIGINT NOT NULL,
+          net_reward BIGINT NOT NULL,
+          net_base_reward BIGINT NOT NULL,
+          net_miner_boost_reward BIGINT NOT NULL,
+          net_staker_boost_reward BIGINT NOT NULL,
+          PRIMARY KEY (pool_address, last_hash_at)
+        );
+    END IF;
+END
+$$;
+
+-- create mining event members table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'mining_event_members') THEN
+        CREATE TABLE mining_event_members (
+          pool_address VARCHAR NOT NULL,
+          last_hash_at BIGINT NOT NULL,
+          member VARCHAR NOT NULL,
+          score BIGINT NOT NULL,
+          reward BIGINT NOT NULL,
+          PRIMARY KEY (pool_address, last_hash_at, member),
+          FOREIGN KEY (pool_address, last_hash_at)
+            REFERENCES mining_events(pool_address, last_hash_at) ON DELETE CASCADE
+        );
+    END IF;
+END
+$$;
+
+-- create indexes on mining events timestamp and mining event members member
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_indexes WHERE indexname = 'mining_events_timestamp_idx') THEN
+        CREATE INDEX mining_events_timestamp_idx ON mining_events(pool_address, timestamp);
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_indexes WHERE indexname = 'mining_event_members_member_idx') THEN
+        CREATE INDEX mining_event_members_member_idx ON mining_event_members(pool_address, member, last_hash_at);
+    END IF;
+END
+$$;
+
+-- create processed events table
+-- seeded from the mining events recorded before it existed
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'processed_events') THEN
+        CREATE TABLE processed_events (
+          pool_address VARCHAR NOT NULL,
+          signature VARCHAR NOT NULL,
+          last_hash_at BIGINT NOT NULL,
+          processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+          PRIMARY KEY (pool_address, signature),
+          CONSTRAINT unique_processed_last_hash_at UNIQUE (pool_address, last_hash_at)
+        );
+        INSERT INTO processed_events (pool_address, signature, last_hash_at)
+        SELECT pool_address, signature, last_hash_at FROM mining_events
+        ON CONFLICT DO NOTHING;
+    END IF;
+END
+$$;
+
+-- create quarantined events table
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'quarantined_events') THEN
+        CREATE TABLE quarantined_events (
+          pool_address VARCHAR NOT NULL,
+          signature VARCHAR NOT NULL,
+          last_hash_at BIGINT NOT NULL,
+          source VARCHAR NOT NULL,
+          reason VARCHAR NOT NULL,
+          mine_event BYTEA NOT NULL,
+          created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+          PRIMARY KEY (pool_address, signature, source)
+        );
+    END IF;
+END
+$$;
+
+-- create bans table
+-- rows outlive the ban itself, so repeat offences escalate
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'bans') THEN
+        CREATE TABLE bans (
+          pool_address VARCHAR NOT NULL,
+          subject VARCHAR NOT NULL,
+          reason VARCHAR NOT NULL,
+          offences INTEGER NOT NULL,
+          banned_at BIGINT NOT NULL,
+          expires_at BIGINT NOT NULL,
+          PRIMARY KEY (pool_address, subject)
+        );
+    END IF;
+END
+$$;
+
+-- notify the servers of member changes, to keep their member directories fresh.
+-- balance updates are left out, as they touch every member on each mining event
+CREATE OR REPLACE FUNCTION notify_member_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM pg_notify('members', json_build_object(
+          'op', TG_OP,
+          'authority', OLD.authority,
+          'old_authority', NULL,
+          'pool_address', OLD.pool_address,
+          'id', OLD.id,
+          'is_approved', OLD.is_approved,
+          'is_kyc', OLD.is_kyc
+        )::text);
+        RETURN OLD;
+    END IF;
+    PERFORM pg_notify('members', json_build_object(
+      'op', TG_OP,
+      'authority', NEW.authority,
+      'old_authority', CASE WHEN TG_OP = 'UPDATE' THEN OLD.authority ELSE NULL END,
+      'pool_address', NEW.pool_address,
+      'id', NEW.id,
+      'is_approved', NEW.is_approved,
+      'is_kyc', NEW.is_kyc
+    )::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_trigger WHERE tgname = 'members_notify') THEN
+        CREATE TRIGGER members_notify
+        AFTER INSERT OR DELETE OR UPDATE OF id, authority, is_approved, is_kyc ON members
+        FOR EACH ROW EXECUTE FUNCTION notify_member_change();
+    END IF;
+END
+$$;
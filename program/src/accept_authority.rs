@@ -0,0 +1,23 @@
+use ore_pool_api::prelude::*;
+use steel::*;
+
+/// AcceptAuthority completes a transfer of the pool to the nominated authority.
+///
+/// The pool address and CPI signer seeds are unchanged, as they are derived from the
+/// seed authority the pool was launched with.
+pub fn process_accept_authority(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    // Load accounts.
+    let [signer_info, pool_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let pool = pool_info
+        .as_account_mut::<Pool>(&ore_pool_api::ID)?
+        .assert_mut(|p| p.pending_authority == *signer_info.key)?;
+
+    // Transfer authority.
+    pool.authority = pool.pending_authority;
+    pool.pending_authority = Pubkey::default();
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use ore_api::prelude::*;
 use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
 use steel::*;
 
 /// Attribute updates a member's claimable balance.
@@ -36,6 +37,18 @@ pub fn process_attribute(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramRe
     // Update claimable balance
     pool.total_rewards += balance_change;
 
+    // Log the attribution for parsing.
+    let event = AttributeEvent {
+        disc: PoolEventType::Attribute as u64,
+        authority: member.authority,
+        member: *member_info.key,
+        pool: *pool_info.key,
+        balance_change,
+        balance: member.balance,
+        total_balance: member.total_balance,
+    };
+    sol_log_data(&[event.to_bytes()]);
+
     // Calculate the total reserves of the pool.
     let reserves = if pool_tokens_info.data_is_empty() {
         proof.balance
@@ -0,0 +1,71 @@
+use ore_api::prelude::*;
+use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
+use steel::*;
+
+/// AttributeMany updates the claimable balances of a batch of members.
+///
+/// The member accounts are passed as remaining accounts and the instruction data carries
+/// each member's lifetime earnings in the same order. Like attribute, each update is
+/// idempotent, but the reserve check is only run once for the whole batch.
+pub fn process_attribute_many(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Load accounts.
+    let [signer_info, pool_info, pool_tokens_info, proof_info, member_infos @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let pool = pool_info
+        .as_account_mut::<Pool>(&ore_pool_api::ID)?
+        .assert_mut(|p| p.authority == *signer_info.key)?;
+    let proof = proof_info
+        .as_account::<Proof>(&ore_api::ID)?
+        .assert(|p| p.authority == *pool_info.key)?;
+
+    // Parse args.
+    if data.len() != member_infos.len() * 8 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Update balances idempotently
+    for (member_info, total_balance) in member_infos.iter().zip(data.chunks_exact(8)) {
+        let total_balance = u64::from_le_bytes(total_balance.try_into().unwrap());
+        let member = member_info
+            .as_account_mut::<Member>(&ore_pool_api::ID)?
+            .assert_mut(|m| m.pool == *pool_info.key)?
+            .assert_mut(|m| total_balance >= m.total_balance)?;
+        let balance_change = total_balance - member.total_balance;
+        member.balance += balance_change;
+        member.total_balance = total_balance;
+
+        // Update claimable balance
+        pool.total_rewards += balance_change;
+
+        // Log the attribution for parsing.
+        let event = AttributeEvent {
+            disc: PoolEventType::Attribute as u64,
+            authority: member.authority,
+            member: *member_info.key,
+            pool: *pool_info.key,
+            balance_change,
+            balance: member.balance,
+            total_balance: member.total_balance,
+        };
+        sol_log_data(&[event.to_bytes()]);
+    }
+
+    // Calculate the total reserves of the pool.
+    let reserves = if pool_tokens_info.data_is_empty() {
+        proof.balance
+    } else {
+        let pool_tokens =
+            pool_tokens_info.as_associated_token_account(pool_info.key, &MINT_ADDRESS)?;
+        proof.balance + pool_tokens.amount()
+    };
+
+    // Validate there are enough reserves to cover the total rewards owed to miners.
+    if pool.total_rewards > reserves {
+        return Err(PoolError::AttributionTooLarge.into());
+    }
+
+    Ok(())
+}
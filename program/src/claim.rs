@@ -1,5 +1,6 @@
 use ore_api::prelude::*;
 use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
 use steel::*;
 
 /// Claim allows a member to claim their ORE rewards from the pool.
@@ -57,7 +58,7 @@ pub fn process_claim(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult
                 beneficiary_info,
                 token_program,
                 amount_claimed,
-                &[POOL, pool.authority.as_ref()],
+                &[POOL, pool.seed_authority.as_ref()],
             )?;
         }
     }
@@ -76,9 +77,20 @@ pub fn process_claim(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult
                 token_program.clone(),
             ],
             &ore_pool_api::ID,
-            &[POOL, pool.authority.as_ref()],
+            &[POOL, pool.seed_authority.as_ref()],
         )?;
     }
 
+    // Log the claim for parsing.
+    let event = ClaimEvent {
+        disc: PoolEventType::Claim as u64,
+        authority: member.authority,
+        member: *member_info.key,
+        beneficiary: *beneficiary_info.key,
+        amount,
+        balance: member.balance,
+    };
+    sol_log_data(&[event.to_bytes()]);
+
     Ok(())
 }
@@ -0,0 +1,95 @@
+use ore_api::prelude::*;
+use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
+use steel::*;
+
+use crate::claim::process_claim;
+
+/// ClaimWithProof allows a member to claim their ORE rewards by proving their lifetime
+/// earnings against the pool balance root.
+///
+/// The proven earnings are credited to the member the same way attribute does, so
+/// the member account keeps track of what has already been claimed and stale proofs
+/// cannot be credited twice. The proven leaf commits to the member id, so earnings from a
+/// closed member account cannot be claimed against a new one. The claim itself is then
+/// processed as usual.
+pub fn process_claim_with_proof(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args.
+    if data.len() < std::mem::size_of::<ClaimWithProof>() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (args, balance_proof) = data.split_at(std::mem::size_of::<ClaimWithProof>());
+    let args = ClaimWithProof::try_from_bytes(args)?;
+    let total_balance = u64::from_le_bytes(args.total_balance);
+    let balance_proof: &[[u8; 32]] = bytemuck::try_cast_slice(balance_proof)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // Load accounts.
+    let [signer_info, _beneficiary_info, member_info, pool_info, pool_tokens_info, proof_info, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    {
+        let member = member_info
+            .as_account_mut::<Member>(&ore_pool_api::ID)?
+            .assert_mut(|m| m.authority == *signer_info.key)?
+            .assert_mut(|m| m.pool == *pool_info.key)?;
+        let pool = pool_info.as_account_mut::<Pool>(&ore_pool_api::ID)?;
+        let proof = proof_info
+            .as_account::<Proof>(&ore_api::ID)?
+            .assert(|p| p.authority == *pool_info.key)?;
+
+        // Verify the lifetime earnings against the balance root.
+        let leaf = balance_leaf(&member.authority, member.id, total_balance);
+        if !verify(&pool.balance_root, leaf, balance_proof) {
+            return Err(PoolError::InvalidBalanceProof.into());
+        }
+
+        // Update balance idempotently.
+        // The proof may lag behind attributions, in which case there is nothing to credit.
+        if total_balance > member.total_balance {
+            let balance_change = total_balance - member.total_balance;
+            member.balance += balance_change;
+            member.total_balance = total_balance;
+
+            // Update claimable balance
+            pool.total_rewards += balance_change;
+
+            // Log the attribution for parsing.
+            let event = AttributeEvent {
+                disc: PoolEventType::Attribute as u64,
+                authority: member.authority,
+                member: *member_info.key,
+                pool: *pool_info.key,
+                balance_change,
+                balance: member.balance,
+                total_balance: member.total_balance,
+            };
+            sol_log_data(&[event.to_bytes()]);
+
+            // Calculate the total reserves of the pool.
+            let reserves = if pool_tokens_info.data_is_empty() {
+                proof.balance
+            } else {
+                let pool_tokens =
+                    pool_tokens_info.as_associated_token_account(pool_info.key, &MINT_ADDRESS)?;
+                proof.balance + pool_tokens.amount()
+            };
+
+            // Validate there are enough reserves to cover the total rewards owed to miners.
+            if pool.total_rewards > reserves {
+                return Err(PoolError::AttributionTooLarge.into());
+            }
+        }
+    }
+
+    // Claim from the credited balance.
+    #[allow(deprecated)]
+    let claim_args = ore_pool_api::instruction::Claim {
+        amount: args.amount,
+        pool_bump: 0,
+    };
+    process_claim(accounts, bytemuck::bytes_of(&claim_args))
+}
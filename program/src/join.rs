@@ -1,4 +1,5 @@
 use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
 use steel::*;
 
 /// Join creates a new account for a pool participant.
@@ -39,10 +40,21 @@ pub fn process_join(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult
     member.balance = 0;
     member.total_balance = 0;
     member.pool = *pool_info.key;
-    member.id = pool.total_members; // zero index
+    member.id = pool.next_member_id; // zero index
 
     // Update total pool member count.
+    pool.next_member_id = pool.next_member_id.checked_add(1).unwrap();
     pool.total_members = pool.total_members.checked_add(1).unwrap();
 
+    // Log the new member for parsing.
+    let event = JoinEvent {
+        disc: PoolEventType::Join as u64,
+        authority: member.authority,
+        member: *member_info.key,
+        pool: *pool_info.key,
+        id: member.id,
+    };
+    sol_log_data(&[event.to_bytes()]);
+
     Ok(())
 }
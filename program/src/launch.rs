@@ -6,6 +6,10 @@ use steel::*;
 pub fn process_launch(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
     // Parse args.
     let args = Launch::try_from_bytes(data)?;
+    let commission = u64::from_le_bytes(args.commission);
+    if commission > MAX_COMMISSION {
+        return Err(PoolError::CommissionTooLarge.into());
+    }
 
     // Load accounts.
     let [signer_info, miner_info, pool_info, proof_info, ore_program, ore_boost_program, token_program, associated_token_program, system_program, slot_hashes_sysvar] =
@@ -57,9 +61,15 @@ pub fn process_launch(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
         let pool = pool_info.as_account_mut::<Pool>(&ore_pool_api::ID)?;
         pool.attestation = [0; 32];
         pool.authority = *signer_info.key;
+        pool.seed_authority = *signer_info.key;
+        pool.pending_authority = Pubkey::default();
         pool.last_total_members = 0;
         pool.last_hash_at = proof.last_hash_at;
         pool.url = args.url;
+        pool.commission = commission;
+        pool.pending_commission = 0;
+        pool.commission_effective_at = 0;
+        pool.balance_root = [0; 32];
     }
 
     Ok(())
@@ -0,0 +1,39 @@
+use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
+use steel::*;
+
+/// Leave closes a member account and returns its rent to the member.
+///
+/// The member must claim their full balance first. Their id is not reused.
+pub fn process_leave(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    // Load accounts.
+    let [signer_info, member_info, pool_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let member = member_info
+        .is_writable()?
+        .as_account::<Member>(&ore_pool_api::ID)?
+        .assert(|m| m.authority == *signer_info.key)?
+        .assert(|m| m.pool == *pool_info.key)?
+        .assert_err(|m| m.balance == 0, PoolError::MemberBalanceNotZero.into())?;
+    let pool = pool_info.as_account_mut::<Pool>(&ore_pool_api::ID)?;
+
+    // Update total pool member count.
+    pool.total_members = pool.total_members.checked_sub(1).unwrap();
+
+    // Log the leave for parsing.
+    let event = LeaveEvent {
+        disc: PoolEventType::Leave as u64,
+        authority: member.authority,
+        member: *member_info.key,
+        pool: *pool_info.key,
+        id: member.id,
+    };
+    sol_log_data(&[event.to_bytes()]);
+
+    // Close member account and return rent to the member.
+    member_info.close(signer_info)?;
+
+    Ok(())
+}
@@ -1,24 +1,44 @@
+mod accept_authority;
 mod attribute;
+mod attribute_many;
 mod claim;
+mod claim_with_proof;
 mod commit;
 mod join;
 mod launch;
+mod leave;
+mod migrate_pool;
 mod open_share;
 mod open_stake;
+mod propose_authority;
 mod stake;
 mod submit;
+mod transfer_member;
 mod unstake;
+mod update_balance_root;
+mod update_commission;
+mod update_pool;
 
+use accept_authority::*;
 use attribute::*;
+use attribute_many::*;
 use claim::*;
+use claim_with_proof::*;
 use commit::*;
 use join::*;
 use launch::*;
+use leave::*;
+use migrate_pool::*;
 use open_share::*;
 use open_stake::*;
+use propose_authority::*;
 use stake::*;
 use submit::*;
+use transfer_member::*;
 use unstake::*;
+use update_balance_root::*;
+use update_commission::*;
+use update_pool::*;
 
 use ore_pool_api::prelude::*;
 use steel::*;
@@ -37,13 +57,25 @@ pub fn process_instruction(
         PoolInstruction::OpenShare => process_open_share(accounts, data)?,
         PoolInstruction::Stake => process_stake(accounts, data)?,
         PoolInstruction::Unstake => process_unstake(accounts, data)?,
+        PoolInstruction::TransferMember => process_transfer_member(accounts, data)?,
+        PoolInstruction::Leave => process_leave(accounts, data)?,
+        PoolInstruction::ClaimWithProof => process_claim_with_proof(accounts, data)?,
 
         // Admin
         PoolInstruction::Attribute => process_attribute(accounts, data)?,
+        PoolInstruction::AttributeMany => process_attribute_many(accounts, data)?,
         PoolInstruction::Commit => process_commit(accounts, data)?,
         PoolInstruction::Launch => process_launch(accounts, data)?,
         PoolInstruction::OpenStake => process_open_stake(accounts, data)?,
         PoolInstruction::Submit => process_submit(accounts, data)?,
+        PoolInstruction::UpdatePool => process_update_pool(accounts, data)?,
+        PoolInstruction::ProposeAuthority => process_propose_authority(accounts, data)?,
+        PoolInstruction::AcceptAuthority => process_accept_authority(accounts, data)?,
+        PoolInstruction::UpdateCommission => process_update_commission(accounts, data)?,
+        PoolInstruction::UpdateBalanceRoot => process_update_balance_root(accounts, data)?,
+
+        // Migration
+        PoolInstruction::MigratePool => process_migrate_pool(accounts, data)?,
 
         _ => panic!("Temporarily disabled for migration."),
     }
@@ -0,0 +1,84 @@
+use ore_pool_api::prelude::*;
+use solana_program::{rent::Rent, system_instruction, sysvar::Sysvar};
+use steel::*;
+
+/// The size of a pool account before the seed and pending authorities were added.
+const POOL_SIZE_V0: usize = 8 + 240;
+
+/// The size of a pool account before the next member id was added.
+const POOL_SIZE_V1: usize = 8 + 304;
+
+/// The size of a pool account before the commission was recorded on chain.
+const POOL_SIZE_V2: usize = 8 + 312;
+
+/// The size of a pool account before the balance root was added.
+const POOL_SIZE_V3: usize = 8 + 336;
+
+/// MigratePool reallocates a pool account to the latest layout.
+///
+/// New fields are backfilled from the existing state so the pool keeps working as before.
+/// The commission was previously only known off chain, so it starts at zero and is
+/// raised by the pool authority through the timelocked update commission instruction.
+pub fn process_migrate_pool(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    // Load accounts.
+    let [signer_info, pool_info, system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    pool_info.is_writable()?.has_owner(&ore_pool_api::ID)?;
+    system_program.is_program(&system_program::ID)?;
+
+    // Validate the legacy layout. The authority is the first field in every version.
+    let old_size = pool_info.data_len();
+    let authority = {
+        let data = pool_info.data.borrow();
+        if old_size < POOL_SIZE_V0 || data[0].ne(&Pool::discriminator()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Pubkey::new_from_array(data[8..40].try_into().unwrap())
+    };
+    if signer_info.key.ne(&authority) && signer_info.key.ne(&ADMIN_ADDRESS) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Exit early if already migrated.
+    let new_size = 8 + std::mem::size_of::<Pool>();
+    if old_size >= new_size {
+        return Ok(());
+    }
+
+    // Fund rent for the new size and reallocate.
+    let rent = Rent::get()?.minimum_balance(new_size);
+    let lamports = rent.saturating_sub(pool_info.lamports());
+    if lamports > 0 {
+        solana_program::program::invoke(
+            &system_instruction::transfer(signer_info.key, pool_info.key, lamports),
+            &[
+                signer_info.clone(),
+                pool_info.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+    pool_info.realloc(new_size, true)?;
+
+    // Backfill new fields.
+    let pool = pool_info.as_account_mut::<Pool>(&ore_pool_api::ID)?;
+    if old_size <= POOL_SIZE_V0 {
+        pool.seed_authority = pool.authority;
+        pool.pending_authority = Pubkey::default();
+    }
+    if old_size <= POOL_SIZE_V1 {
+        pool.next_member_id = pool.total_members;
+    }
+    if old_size <= POOL_SIZE_V2 {
+        pool.commission = 0;
+        pool.pending_commission = 0;
+        pool.commission_effective_at = 0;
+    }
+    if old_size <= POOL_SIZE_V3 {
+        pool.balance_root = [0; 32];
+    }
+
+    Ok(())
+}
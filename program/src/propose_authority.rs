@@ -0,0 +1,25 @@
+use ore_pool_api::prelude::*;
+use steel::*;
+
+/// ProposeAuthority nominates a new authority to take over the pool.
+///
+/// The transfer only takes effect once the nominated authority accepts it. Proposing
+/// the default pubkey cancels a pending transfer.
+pub fn process_propose_authority(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args.
+    let args = ProposeAuthority::try_from_bytes(data)?;
+
+    // Load accounts.
+    let [signer_info, pool_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let pool = pool_info
+        .as_account_mut::<Pool>(&ore_pool_api::ID)?
+        .assert_mut(|p| p.authority == *signer_info.key)?;
+
+    // Nominate new authority.
+    pool.pending_authority = args.new_authority;
+
+    Ok(())
+}
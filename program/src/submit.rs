@@ -1,6 +1,7 @@
 use drillx::Solution;
 use ore_api::prelude::*;
 use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
 use steel::*;
 
 /// Submit sends the pool's best hash to the ORE mining contract.
@@ -63,5 +64,17 @@ pub fn process_submit(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResul
     pool.last_total_members = pool.total_members;
     pool.total_submissions += 1;
 
+    // Log the submission for parsing.
+    let event = SubmitEvent {
+        disc: PoolEventType::Submit as u64,
+        authority: *signer_info.key,
+        pool: *pool_info.key,
+        attestation: pool.attestation,
+        last_hash_at: pool.last_hash_at,
+        total_submissions: pool.total_submissions,
+        total_members: pool.total_members,
+    };
+    sol_log_data(&[event.to_bytes()]);
+
     Ok(())
 }
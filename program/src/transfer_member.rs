@@ -0,0 +1,73 @@
+use ore_pool_api::prelude::*;
+use solana_program::log::sol_log_data;
+use steel::*;
+
+/// TransferMember hands a member account and its claimable balance to a new authority.
+///
+/// The record is moved to the member account derived from the new authority, so the
+/// member id and balances are preserved while the old account is closed.
+pub fn process_transfer_member(accounts: &[AccountInfo<'_>], _data: &[u8]) -> ProgramResult {
+    // Load accounts.
+    let [signer_info, new_authority_info, member_info, new_member_info, pool_info, system_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let member = member_info
+        .is_writable()?
+        .as_account::<Member>(&ore_pool_api::ID)?
+        .assert(|m| m.authority == *signer_info.key)?
+        .assert(|m| m.pool == *pool_info.key)?;
+    new_member_info.is_empty()?.is_writable()?.has_seeds(
+        &[
+            MEMBER,
+            new_authority_info.key.as_ref(),
+            pool_info.key.as_ref(),
+        ],
+        &ore_pool_api::ID,
+    )?;
+    pool_info.as_account::<Pool>(&ore_pool_api::ID)?;
+    system_program.is_program(&system_program::ID)?;
+
+    // Copy the record before the old account is closed.
+    let (id, balance, total_balance) = (member.id, member.balance, member.total_balance);
+
+    // Initialize new member account
+    create_program_account::<Member>(
+        new_member_info,
+        system_program,
+        signer_info,
+        &ore_pool_api::ID,
+        &[
+            MEMBER,
+            new_authority_info.key.as_ref(),
+            pool_info.key.as_ref(),
+        ],
+    )?;
+    let new_member = new_member_info.as_account_mut::<Member>(&ore_pool_api::ID)?;
+    new_member.authority = *new_authority_info.key;
+    new_member.balance = balance;
+    new_member.total_balance = total_balance;
+    new_member.pool = *pool_info.key;
+    new_member.id = id;
+
+    // Log the transfer for parsing.
+    let event = TransferMemberEvent {
+        disc: PoolEventType::TransferMember as u64,
+        authority: *signer_info.key,
+        new_authority: *new_authority_info.key,
+        member: *member_info.key,
+        new_member: *new_member_info.key,
+        pool: *pool_info.key,
+        id,
+        balance,
+        total_balance,
+    };
+    sol_log_data(&[event.to_bytes()]);
+
+    // Close old member account and return rent to the signer.
+    member_info.close(signer_info)?;
+
+    Ok(())
+}
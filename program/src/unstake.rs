@@ -67,7 +67,7 @@ pub fn process_unstake(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResu
                     AccountMeta::new_readonly(*token_program.key, false),
                 ],
                 data: [
-                    [3 as u8].to_vec(),
+                    [3_u8].to_vec(),
                     bytemuck::bytes_of(&withdraw_amount).to_vec(),
                 ]
                 .concat(),
@@ -82,7 +82,7 @@ pub fn process_unstake(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResu
                 token_program.clone(),
             ],
             &ore_pool_api::ID,
-            &[POOL, pool.authority.as_ref()],
+            &[POOL, pool.seed_authority.as_ref()],
         )?;
     }
 
@@ -93,7 +93,7 @@ pub fn process_unstake(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResu
         recipient_tokens_info,
         token_program,
         amount,
-        &[POOL, pool.authority.as_ref()],
+        &[POOL, pool.seed_authority.as_ref()],
     )?;
 
     // Log the balance for parsing.
@@ -0,0 +1,25 @@
+use ore_pool_api::prelude::*;
+use steel::*;
+
+/// UpdateBalanceRoot posts the Merkle root of every member's lifetime earnings.
+///
+/// This is an alternative to attributing each member individually. Members prove their
+/// lifetime earnings against the root when they claim.
+pub fn process_update_balance_root(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args.
+    let args = UpdateBalanceRoot::try_from_bytes(data)?;
+
+    // Load accounts.
+    let [signer_info, pool_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let pool = pool_info
+        .as_account_mut::<Pool>(&ore_pool_api::ID)?
+        .assert_mut(|p| p.authority == *signer_info.key)?;
+
+    // Update root.
+    pool.balance_root = args.root;
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use ore_pool_api::prelude::*;
+use steel::*;
+
+/// UpdateCommission changes the operator commission.
+///
+/// Decreases take effect immediately. Increases are timelocked so members have time to
+/// leave the pool before paying the new rate, and replace any increase already pending.
+pub fn process_update_commission(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args.
+    let args = UpdateCommission::try_from_bytes(data)?;
+    let commission = u64::from_le_bytes(args.commission);
+    if commission > MAX_COMMISSION {
+        return Err(PoolError::CommissionTooLarge.into());
+    }
+
+    // Load accounts.
+    let [signer_info, pool_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let pool = pool_info
+        .as_account_mut::<Pool>(&ore_pool_api::ID)?
+        .assert_mut(|p| p.authority == *signer_info.key)?;
+
+    // Apply a pending change whose timelock has elapsed.
+    let clock = Clock::get()?;
+    pool.commission = pool.commission_at(clock.unix_timestamp);
+
+    // Schedule or apply the new commission.
+    if commission > pool.commission {
+        pool.pending_commission = commission;
+        pool.commission_effective_at = clock
+            .unix_timestamp
+            .checked_add(COMMISSION_TIMELOCK)
+            .unwrap();
+    } else {
+        pool.commission = commission;
+        pool.pending_commission = 0;
+        pool.commission_effective_at = 0;
+    }
+
+    Ok(())
+}
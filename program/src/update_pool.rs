@@ -0,0 +1,22 @@
+use ore_pool_api::prelude::*;
+use steel::*;
+
+/// UpdatePool allows the pool authority to change the url where hashes should be submitted.
+pub fn process_update_pool(accounts: &[AccountInfo<'_>], data: &[u8]) -> ProgramResult {
+    // Parse args.
+    let args = UpdatePool::try_from_bytes(data)?;
+
+    // Load accounts.
+    let [signer_info, pool_info] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    signer_info.is_signer()?;
+    let pool = pool_info
+        .as_account_mut::<Pool>(&ore_pool_api::ID)?
+        .assert_mut(|p| p.authority == *signer_info.key)?;
+
+    // Update url.
+    pool.url = args.url;
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+struct Setup {
+    context: ProgramTestContext,
+    authority: Keypair,
+    pool: Pubkey,
+    members: [Pubkey; 2],
+}
+
+async fn setup(proof_balance: u64, pool_tokens: Option<u64>) -> Setup {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let pool = add_pool(&mut test, &pool(authority.pubkey()));
+    add_proof(&mut test, pool, proof_balance);
+    if let Some(amount) = pool_tokens {
+        add_mint(&mut test);
+        add_tokens(&mut test, pool_tokens_address(pool), pool, amount);
+    }
+    let members = [Pubkey::new_unique(), Pubkey::new_unique()];
+    for (id, member) in members.iter().enumerate() {
+        add_member(&mut test, *member, pool, id as u64, 0, 0);
+    }
+    Setup {
+        context: test.start_with_context().await,
+        authority,
+        pool,
+        members,
+    }
+}
+
+#[tokio::test]
+async fn attribute_many_credits_each_member() {
+    let Setup {
+        mut context,
+        authority,
+        pool,
+        members: [a, b],
+    } = setup(1_000, None).await;
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 100), (b, 200)]);
+    process(&mut context, &[ix.clone()], &[&authority])
+        .await
+        .unwrap();
+    let member = get::<Member>(&mut context, member_pda(a, pool).0).await;
+    assert_eq!((member.balance, member.total_balance), (100, 100));
+    let member = get::<Member>(&mut context, member_pda(b, pool).0).await;
+    assert_eq!((member.balance, member.total_balance), (200, 200));
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 300);
+
+    // idempotent
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 300);
+
+    // only the change is credited
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 150)]);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let member = get::<Member>(&mut context, member_pda(a, pool).0).await;
+    assert_eq!((member.balance, member.total_balance), (150, 150));
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 350);
+}
+
+#[tokio::test]
+async fn attribute_many_rejects_data_that_does_not_match_the_members() {
+    let Setup {
+        mut context,
+        authority,
+        pool,
+        members: [a, b],
+    } = setup(1_000, None).await;
+
+    // a balance without a member
+    let mut ix = attribute_many(authority.pubkey(), pool, &[(a, 100)]);
+    ix.data.extend_from_slice(&200u64.to_le_bytes());
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidInstructionData);
+
+    // a member without a balance
+    let mut ix = attribute_many(authority.pubkey(), pool, &[(a, 100), (b, 200)]);
+    ix.data.truncate(ix.data.len() - 8);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidInstructionData);
+
+    // a partial balance
+    let mut ix = attribute_many(authority.pubkey(), pool, &[(a, 100)]);
+    ix.data.pop();
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidInstructionData);
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 0);
+}
+
+#[tokio::test]
+async fn attribute_many_checks_the_reserves_of_the_batch() {
+    let Setup {
+        mut context,
+        authority,
+        pool,
+        members: [a, b],
+    } = setup(250, None).await;
+
+    // each attribution is covered, but not the batch
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 100), (b, 200)]);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, PoolError::AttributionTooLarge);
+    let member = get::<Member>(&mut context, member_pda(a, pool).0).await;
+    assert_eq!(member.total_balance, 0);
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 0);
+
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 100), (b, 150)]);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 250);
+}
+
+#[tokio::test]
+async fn attribute_many_counts_pool_tokens_as_reserves() {
+    let Setup {
+        mut context,
+        authority,
+        pool,
+        members: [a, b],
+    } = setup(250, Some(50)).await;
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 100), (b, 200)]);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 300);
+
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 101)]);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, PoolError::AttributionTooLarge);
+}
+
+#[tokio::test]
+async fn attribute_many_rejects_lower_balances() {
+    let Setup {
+        mut context,
+        authority,
+        pool,
+        members: [a, b],
+    } = setup(1_000, None).await;
+    let ix = attribute_many(authority.pubkey(), pool, &[(a, 100)]);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let ix = attribute_many(authority.pubkey(), pool, &[(b, 100), (a, 99)]);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    let member = get::<Member>(&mut context, member_pda(b, pool).0).await;
+    assert_eq!(member.total_balance, 0);
+}
+
+#[tokio::test]
+async fn attribute_many_rejects_other_signers() {
+    let Setup {
+        mut context,
+        pool,
+        members: [a, _],
+        ..
+    } = setup(1_000, None).await;
+    let signer = Keypair::new();
+    let ix = attribute_many(signer.pubkey(), pool, &[(a, 100)]);
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn attribute_many_rejects_members_of_other_pools() {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let pool = add_pool(&mut test, &pool(authority.pubkey()));
+    add_proof(&mut test, pool, 1_000);
+    let other_pool = add_pool(&mut test, &common::pool(Pubkey::new_unique()));
+    let member = Pubkey::new_unique();
+    let other_member = add_member(&mut test, member, other_pool, 0, 0, 0);
+    let mut context = test.start_with_context().await;
+
+    let mut ix = attribute_many(authority.pubkey(), pool, &[(member, 100)]);
+    ix.accounts[4].pubkey = other_member;
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+/// The ORE held by the pool proof and the pool token account.
+const RESERVES: u64 = 1_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    authority: Keypair,
+    pool: Pubkey,
+    member: Keypair,
+    beneficiary: Pubkey,
+    tree: MerkleTree,
+}
+
+/// Sets up a pool with two members, and a balance root crediting the first
+/// member their proven earnings and the second 500.
+async fn setup(
+    proof_balance: u64,
+    member_balance: u64,
+    member_total_balance: u64,
+    proven: u64,
+) -> Setup {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let member = add_signer(&mut test);
+    let other_member = Pubkey::new_unique();
+    let pool_authority = authority.pubkey();
+    let tree = MerkleTree::new(vec![
+        balance_leaf(&member.pubkey(), 0, proven),
+        balance_leaf(&other_member, 1, 500),
+    ]);
+    let pool = add_pool(
+        &mut test,
+        &Pool {
+            total_rewards: member_balance,
+            total_members: 2,
+            next_member_id: 2,
+            balance_root: tree.root(),
+            ..pool(pool_authority)
+        },
+    );
+    add_member(
+        &mut test,
+        member.pubkey(),
+        pool,
+        0,
+        member_balance,
+        member_total_balance,
+    );
+    add_member(&mut test, other_member, pool, 1, 0, 0);
+    add_proof(&mut test, pool, proof_balance);
+    add_mint(&mut test);
+    add_tokens(&mut test, pool_tokens_address(pool), pool, RESERVES);
+    let beneficiary = Pubkey::new_unique();
+    add_tokens(&mut test, beneficiary, member.pubkey(), 0);
+    Setup {
+        context: test.start_with_context().await,
+        authority,
+        pool,
+        member,
+        beneficiary,
+        tree,
+    }
+}
+
+#[tokio::test]
+async fn update_balance_root_sets_the_root() {
+    let Setup {
+        mut context,
+        authority,
+        pool,
+        ..
+    } = setup(RESERVES, 0, 0, 300).await;
+    let ix = update_balance_root(authority.pubkey(), pool, [1; 32]);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(get::<Pool>(&mut context, pool).await.balance_root, [1; 32]);
+
+    let signer = Keypair::new();
+    let ix = update_balance_root(signer.pubkey(), pool, [2; 32]);
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    assert_eq!(get::<Pool>(&mut context, pool).await.balance_root, [1; 32]);
+}
+
+#[tokio::test]
+async fn claim_with_proof_credits_the_proven_earnings() {
+    let Setup {
+        mut context,
+        pool,
+        member,
+        beneficiary,
+        tree,
+        ..
+    } = setup(RESERVES, 0, 0, 300).await;
+    let proof = tree.proof(0).unwrap();
+    let member_address = member_pda(member.pubkey(), pool).0;
+
+    // credited in full, and claimed in part
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 100, 300, &proof);
+    process(&mut context, &[ix], &[&member]).await.unwrap();
+    let state = get::<Member>(&mut context, member_address).await;
+    assert_eq!((state.balance, state.total_balance), (200, 300));
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 200);
+    assert_eq!(token_balance(&mut context, beneficiary).await, 100);
+    assert_eq!(
+        token_balance(&mut context, pool_tokens_address(pool)).await,
+        RESERVES - 100
+    );
+
+    // the same proof is not credited twice
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 200, 300, &proof);
+    process(&mut context, &[ix], &[&member]).await.unwrap();
+    let state = get::<Member>(&mut context, member_address).await;
+    assert_eq!((state.balance, state.total_balance), (0, 300));
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_rewards, 0);
+    assert_eq!(token_balance(&mut context, beneficiary).await, 300);
+}
+
+#[tokio::test]
+async fn claim_with_proof_accepts_proofs_behind_attribution() {
+    // attributed 400 already, 350 of which was claimed
+    let Setup {
+        mut context,
+        pool,
+        member,
+        beneficiary,
+        tree,
+        ..
+    } = setup(RESERVES, 50, 400, 300).await;
+    let proof = tree.proof(0).unwrap();
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 50, 300, &proof);
+    process(&mut context, &[ix], &[&member]).await.unwrap();
+    let state = get::<Member>(&mut context, member_pda(member.pubkey(), pool).0).await;
+    assert_eq!((state.balance, state.total_balance), (0, 400));
+    assert_eq!(token_balance(&mut context, beneficiary).await, 50);
+}
+
+#[tokio::test]
+async fn claim_with_proof_rejects_invalid_proofs() {
+    let Setup {
+        mut context,
+        pool,
+        member,
+        beneficiary,
+        tree,
+        ..
+    } = setup(RESERVES, 0, 0, 300).await;
+    let proof = tree.proof(0).unwrap();
+
+    // more than was earned
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 0, 301, &proof);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, PoolError::InvalidBalanceProof);
+
+    // the earnings of another member
+    let other_proof = tree.proof(1).unwrap();
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 0, 500, &other_proof);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, PoolError::InvalidBalanceProof);
+
+    // no proof
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 0, 300, &[]);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, PoolError::InvalidBalanceProof);
+
+    // a partial node
+    let mut ix = claim_with_proof(member.pubkey(), beneficiary, pool, 0, 300, &proof);
+    ix.data.pop();
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, ProgramError::InvalidInstructionData);
+
+    let state = get::<Member>(&mut context, member_pda(member.pubkey(), pool).0).await;
+    assert_eq!((state.balance, state.total_balance), (0, 0));
+}
+
+#[tokio::test]
+async fn claim_with_proof_rejects_earnings_of_a_previous_membership() {
+    let mut test = program_test();
+    let member = add_signer(&mut test);
+    let tree = MerkleTree::new(vec![balance_leaf(&member.pubkey(), 0, 300)]);
+    let pool = add_pool(
+        &mut test,
+        &Pool {
+            balance_root: tree.root(),
+            next_member_id: 2,
+            ..pool(Pubkey::new_unique())
+        },
+    );
+
+    // the member left and joined again under a new id
+    add_member(&mut test, member.pubkey(), pool, 1, 0, 0);
+    add_proof(&mut test, pool, RESERVES);
+    let beneficiary = Pubkey::new_unique();
+    let mut context = test.start_with_context().await;
+
+    let proof = tree.proof(0).unwrap();
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 0, 300, &proof);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, PoolError::InvalidBalanceProof);
+}
+
+#[tokio::test]
+async fn claim_with_proof_rejects_credits_above_reserves() {
+    // only the pool token account holds ore
+    let Setup {
+        mut context,
+        pool,
+        member,
+        beneficiary,
+        tree,
+        ..
+    } = setup(0, 0, 0, RESERVES + 1).await;
+    let proof = tree.proof(0).unwrap();
+    let ix = claim_with_proof(member.pubkey(), beneficiary, pool, 0, RESERVES + 1, &proof);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, PoolError::AttributionTooLarge);
+}
+
+#[tokio::test]
+async fn claim_with_proof_rejects_other_signers() {
+    let Setup {
+        mut context,
+        pool,
+        member,
+        beneficiary,
+        tree,
+        ..
+    } = setup(RESERVES, 0, 0, 300).await;
+    let signer = Keypair::new();
+    let proof = tree.proof(0).unwrap();
+    let mut ix = claim_with_proof(signer.pubkey(), beneficiary, pool, 0, 300, &proof);
+    ix.accounts[2].pubkey = member_pda(member.pubkey(), pool).0;
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
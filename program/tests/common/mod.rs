@@ -0,0 +1,228 @@
+#![allow(dead_code)]
+
+use ore_api::consts::MINT_ADDRESS;
+use ore_api::state::Proof;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    program_option::COption,
+    program_pack::Pack,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use steel::*;
+
+/// The lamports each test signer starts with.
+const SIGNER_LAMPORTS: u64 = 1_000_000_000;
+
+/// The ORE program is stubbed out, since claims in these tests are paid from pool tokens.
+fn process_ore(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+/// A program test running the pool program natively.
+pub fn program_test() -> ProgramTest {
+    let mut test = ProgramTest::new(
+        "ore_pool_program",
+        ore_pool_api::ID,
+        processor!(ore_pool_program::process_instruction),
+    );
+    test.add_program("ore", ore_api::ID, processor!(process_ore));
+    test.prefer_bpf(false);
+    test
+}
+
+/// Adds a funded system account that can sign and pay for the accounts it opens.
+pub fn add_signer(test: &mut ProgramTest) -> Keypair {
+    let signer = Keypair::new();
+    test.add_account(
+        signer.pubkey(),
+        Account::new(SIGNER_LAMPORTS, 0, &system_program::ID),
+    );
+    signer
+}
+
+/// Adds an account with the given data, funded for rent.
+pub fn add_data(test: &mut ProgramTest, address: Pubkey, owner: Pubkey, data: Vec<u8>) {
+    test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// The data of a program account.
+pub fn account_data<T: Discriminator + Pod>(state: &T) -> Vec<u8> {
+    let mut data = vec![0; 8];
+    data[0] = T::discriminator();
+    data.extend_from_slice(bytemuck::bytes_of(state));
+    data
+}
+
+/// A freshly launched pool.
+pub fn pool(authority: Pubkey) -> Pool {
+    Pool {
+        authority,
+        seed_authority: authority,
+        ..Pool::zeroed()
+    }
+}
+
+/// Adds the pool at the address derived from its seed authority.
+pub fn add_pool(test: &mut ProgramTest, pool: &Pool) -> Pubkey {
+    let (pool_address, _) = pool_pda(pool.seed_authority);
+    add_data(test, pool_address, ore_pool_api::ID, account_data(pool));
+    pool_address
+}
+
+/// Adds a member at the address derived from its authority.
+pub fn add_member(
+    test: &mut ProgramTest,
+    authority: Pubkey,
+    pool: Pubkey,
+    id: u64,
+    balance: u64,
+    total_balance: u64,
+) -> Pubkey {
+    let (member_address, _) = member_pda(authority, pool);
+    let member = Member {
+        id,
+        pool,
+        authority,
+        balance,
+        total_balance,
+    };
+    add_data(
+        test,
+        member_address,
+        ore_pool_api::ID,
+        account_data(&member),
+    );
+    member_address
+}
+
+/// Adds the ORE proof of the pool holding the given balance.
+pub fn add_proof(test: &mut ProgramTest, pool: Pubkey, balance: u64) -> Pubkey {
+    let (proof_address, _) = pool_proof_pda(pool);
+    let proof = Proof {
+        authority: pool,
+        balance,
+        ..Proof::zeroed()
+    };
+    add_data(test, proof_address, ore_api::ID, account_data(&proof));
+    proof_address
+}
+
+/// Adds the ORE mint.
+pub fn add_mint(test: &mut ProgramTest) {
+    let mint = spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply: u64::MAX,
+        decimals: ore_api::consts::TOKEN_DECIMALS,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    mint.pack_into_slice(&mut data);
+    add_data(test, MINT_ADDRESS, spl_token::ID, data);
+}
+
+/// Adds an ORE token account.
+pub fn add_tokens(test: &mut ProgramTest, address: Pubkey, owner: Pubkey, amount: u64) {
+    let account = spl_token::state::Account {
+        mint: MINT_ADDRESS,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    account.pack_into_slice(&mut data);
+    add_data(test, address, spl_token::ID, data);
+}
+
+/// The ORE token account of the pool.
+pub fn pool_tokens_address(pool: Pubkey) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address(&pool, &MINT_ADDRESS)
+}
+
+/// Processes the instructions in a transaction of their own,
+/// under a new blockhash so repeated instructions are not deduplicated.
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(tx).await
+}
+
+/// Asserts the transaction failed with the given program error.
+pub fn assert_error(result: Result<(), BanksClientError>, error: impl Into<ProgramError>) {
+    let error = error.into();
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(_, err) => {
+            assert_eq!(ProgramError::try_from(err), Ok(error))
+        }
+        err => panic!("unexpected transaction error: {:?}", err),
+    }
+}
+
+/// Asserts the transaction failed with the given instruction error, for errors
+/// without a program error counterpart.
+pub fn assert_instruction_error(result: Result<(), BanksClientError>, error: InstructionError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, error)
+    );
+}
+
+/// Reads a program account.
+pub async fn get<T: Discriminator + Pod>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .expect("account exists");
+    assert_eq!(account.data[0], T::discriminator());
+    *bytemuck::from_bytes::<T>(&account.data[8..])
+}
+
+/// Reads the balance of a token account.
+pub async fn token_balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .expect("account exists");
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+/// Sets the unix timestamp of the clock.
+pub async fn set_time(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    context.set_sysvar(&Clock {
+        unix_timestamp,
+        ..clock
+    });
+}
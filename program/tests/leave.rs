@@ -0,0 +1,135 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+struct Setup {
+    context: ProgramTestContext,
+    member: Keypair,
+    pool: Pubkey,
+}
+
+async fn setup(balance: u64) -> Setup {
+    let mut test = program_test();
+    let member = add_signer(&mut test);
+    let pool = add_pool(
+        &mut test,
+        &Pool {
+            total_members: 2,
+            next_member_id: 2,
+            ..pool(Pubkey::new_unique())
+        },
+    );
+    add_member(&mut test, member.pubkey(), pool, 1, balance, 300);
+    Setup {
+        context: test.start_with_context().await,
+        member,
+        pool,
+    }
+}
+
+#[tokio::test]
+async fn leave_closes_the_member_account() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(0).await;
+    let member_address = member_pda(member.pubkey(), pool).0;
+    let rent = context
+        .banks_client
+        .get_balance(member_address)
+        .await
+        .unwrap();
+    let lamports = context
+        .banks_client
+        .get_balance(member.pubkey())
+        .await
+        .unwrap();
+
+    let ix = leave(member.pubkey(), pool);
+    process(&mut context, &[ix], &[&member]).await.unwrap();
+
+    // closed, with the rent returned to the member
+    assert!(context
+        .banks_client
+        .get_account(member_address)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(member.pubkey())
+            .await
+            .unwrap(),
+        lamports + rent
+    );
+
+    // the member count drops, but ids are not reused
+    let state = get::<Pool>(&mut context, pool).await;
+    assert_eq!(state.total_members, 1);
+    assert_eq!(state.next_member_id, 2);
+}
+
+#[tokio::test]
+async fn leave_requires_a_zero_balance() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(1).await;
+    let ix = leave(member.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, PoolError::MemberBalanceNotZero);
+    let state = get::<Member>(&mut context, member_pda(member.pubkey(), pool).0).await;
+    assert_eq!(state.balance, 1);
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_members, 2);
+}
+
+#[tokio::test]
+async fn leave_rejects_other_signers() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(0).await;
+    let signer = Keypair::new();
+    let mut ix = leave(signer.pubkey(), pool);
+    ix.accounts[1].pubkey = member_pda(member.pubkey(), pool).0;
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_members, 2);
+}
+
+#[tokio::test]
+async fn leave_rejects_other_pools() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(0).await;
+    let mut ix = leave(member.pubkey(), pool);
+    ix.accounts[2].pubkey = pool_pda(Pubkey::new_unique()).0;
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn leave_rejects_members_that_already_left() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(0).await;
+    let ix = leave(member.pubkey(), pool);
+    process(&mut context, &[ix.clone()], &[&member])
+        .await
+        .unwrap();
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, ProgramError::InvalidAccountOwner);
+    assert_eq!(get::<Pool>(&mut context, pool).await.total_members, 1);
+}
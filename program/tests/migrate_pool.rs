@@ -0,0 +1,151 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+/// The sizes of pool accounts in each legacy layout.
+const POOL_SIZE_V0: usize = 8 + 240;
+const POOL_SIZE_V1: usize = 8 + 304;
+const POOL_SIZE_V2: usize = 8 + 312;
+const POOL_SIZE_V3: usize = 8 + 336;
+
+/// A pool with every field set, so backfilled fields stand out.
+fn legacy_pool(authority: Pubkey) -> Pool {
+    let mut url = [0; 128];
+    url[..16].copy_from_slice(b"https://ore.pool");
+    Pool {
+        url,
+        attestation: [1; 32],
+        last_hash_at: 2,
+        total_rewards: 3,
+        total_submissions: 4,
+        total_members: 5,
+        last_total_members: 6,
+        seed_authority: authority,
+        pending_authority: Pubkey::new_unique(),
+        next_member_id: 7,
+        commission: 8,
+        pending_commission: 9,
+        commission_effective_at: 10,
+        balance_root: [11; 32],
+        ..pool(authority)
+    }
+}
+
+/// Sets up a pool account truncated to a legacy size.
+async fn setup(size: usize) -> (ProgramTestContext, Keypair, Pubkey, Pool) {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let pool = legacy_pool(authority.pubkey());
+    let pool_address = pool_pda(authority.pubkey()).0;
+    let mut data = account_data(&pool);
+    data.truncate(size);
+    add_data(&mut test, pool_address, ore_pool_api::ID, data);
+    (
+        test.start_with_context().await,
+        authority,
+        pool_address,
+        pool,
+    )
+}
+
+#[tokio::test]
+async fn migrate_pool_backfills_each_legacy_layout() {
+    let new_size = 8 + std::mem::size_of::<Pool>();
+    for size in [
+        POOL_SIZE_V0,
+        POOL_SIZE_V1,
+        POOL_SIZE_V2,
+        POOL_SIZE_V3,
+        new_size,
+    ] {
+        let (mut context, authority, pool_address, pool) = setup(size).await;
+        let ix = migrate_pool(authority.pubkey(), pool_address);
+        process(&mut context, &[ix], &[&authority]).await.unwrap();
+
+        // reallocated and funded for rent
+        let account = context
+            .banks_client
+            .get_account(pool_address)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.data.len(), new_size);
+        let rent = context.banks_client.get_rent().await.unwrap();
+        assert!(rent.is_exempt(account.lamports, new_size));
+
+        // the existing fields are kept and the new ones backfilled
+        let mut expected = pool;
+        if size <= POOL_SIZE_V0 {
+            expected.seed_authority = authority.pubkey();
+            expected.pending_authority = Pubkey::default();
+        }
+        if size <= POOL_SIZE_V1 {
+            expected.next_member_id = pool.total_members;
+        }
+        if size <= POOL_SIZE_V2 {
+            expected.commission = 0;
+            expected.pending_commission = 0;
+            expected.commission_effective_at = 0;
+        }
+        if size <= POOL_SIZE_V3 {
+            expected.balance_root = [0; 32];
+        }
+        assert_eq!(get::<Pool>(&mut context, pool_address).await, expected);
+
+        // migrating again is a no-op
+        let ix = migrate_pool(authority.pubkey(), pool_address);
+        process(&mut context, &[ix], &[&authority]).await.unwrap();
+        assert_eq!(get::<Pool>(&mut context, pool_address).await, expected);
+    }
+}
+
+#[tokio::test]
+async fn migrate_pool_rejects_other_signers() {
+    let (mut context, _, pool_address, _) = setup(POOL_SIZE_V0).await;
+    let signer = Keypair::new();
+    let ix = migrate_pool(signer.pubkey(), pool_address);
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::MissingRequiredSignature);
+    let account = context
+        .banks_client
+        .get_account(pool_address)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), POOL_SIZE_V0);
+}
+
+#[tokio::test]
+async fn migrate_pool_rejects_accounts_that_are_not_pools() {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+
+    // too short to be a pool
+    let short = Pubkey::new_unique();
+    let mut data = account_data(&pool(authority.pubkey()));
+    data.truncate(POOL_SIZE_V0 - 1);
+    add_data(&mut test, short, ore_pool_api::ID, data);
+
+    // a member account
+    let member = add_member(&mut test, authority.pubkey(), short, 0, 0, 0);
+
+    // owned by another program
+    let foreign = Pubkey::new_unique();
+    let data = account_data(&pool(authority.pubkey()));
+    add_data(&mut test, foreign, Pubkey::new_unique(), data);
+
+    let mut context = test.start_with_context().await;
+    for (address, error) in [
+        (short, ProgramError::InvalidAccountData),
+        (member, ProgramError::InvalidAccountData),
+        (foreign, ProgramError::InvalidAccountOwner),
+    ] {
+        let ix = migrate_pool(authority.pubkey(), address);
+        let res = process(&mut context, &[ix], &[&authority]).await;
+        assert_error(res, error);
+    }
+}
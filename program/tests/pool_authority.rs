@@ -0,0 +1,120 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+async fn setup() -> (ProgramTestContext, Keypair, Pubkey) {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let pool = add_pool(&mut test, &pool(authority.pubkey()));
+    (test.start_with_context().await, authority, pool)
+}
+
+#[tokio::test]
+async fn authority_is_handed_off_in_two_steps() {
+    let (mut context, authority, pool) = setup().await;
+    let new_authority = Keypair::new();
+
+    // nominated, but not yet in charge
+    let ix = propose_authority(authority.pubkey(), pool, new_authority.pubkey());
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let state = get::<Pool>(&mut context, pool).await;
+    assert_eq!(state.authority, authority.pubkey());
+    assert_eq!(state.pending_authority, new_authority.pubkey());
+
+    // accepted by the nominee
+    let ix = accept_authority(new_authority.pubkey(), pool);
+    process(&mut context, &[ix], &[&new_authority])
+        .await
+        .unwrap();
+    let state = get::<Pool>(&mut context, pool).await;
+    assert_eq!(state.authority, new_authority.pubkey());
+    assert_eq!(state.pending_authority, Pubkey::default());
+
+    // the pool keeps its address and signer seeds
+    assert_eq!(state.seed_authority, authority.pubkey());
+
+    // the new authority operates the pool, the old one no longer does
+    let ix = update_commission(new_authority.pubkey(), pool, 0);
+    process(&mut context, &[ix], &[&new_authority])
+        .await
+        .unwrap();
+    let ix = update_commission(authority.pubkey(), pool, 0);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+
+    // and cannot take it back
+    let ix = accept_authority(authority.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn propose_authority_rejects_other_signers() {
+    let (mut context, _, pool) = setup().await;
+    let signer = Keypair::new();
+    let ix = propose_authority(signer.pubkey(), pool, signer.pubkey());
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    let state = get::<Pool>(&mut context, pool).await;
+    assert_eq!(state.pending_authority, Pubkey::default());
+}
+
+#[tokio::test]
+async fn accept_authority_rejects_all_but_the_nominee() {
+    let (mut context, authority, pool) = setup().await;
+
+    // nothing to accept
+    let signer = Keypair::new();
+    let ix = accept_authority(signer.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+
+    // someone else was nominated
+    let new_authority = Keypair::new();
+    let ix = propose_authority(authority.pubkey(), pool, new_authority.pubkey());
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let ix = accept_authority(signer.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    let ix = accept_authority(authority.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    assert_eq!(
+        get::<Pool>(&mut context, pool).await.authority,
+        authority.pubkey()
+    );
+}
+
+#[tokio::test]
+async fn propose_authority_can_be_replaced_or_cancelled() {
+    let (mut context, authority, pool) = setup().await;
+    let (first, second) = (Keypair::new(), Keypair::new());
+    let ix = propose_authority(authority.pubkey(), pool, first.pubkey());
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+
+    // the latest nomination replaces the pending one
+    let ix = propose_authority(authority.pubkey(), pool, second.pubkey());
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let ix = accept_authority(first.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&first]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+
+    // nominating the default pubkey cancels
+    let ix = propose_authority(authority.pubkey(), pool, Pubkey::default());
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(
+        get::<Pool>(&mut context, pool).await.pending_authority,
+        Pubkey::default()
+    );
+    let ix = accept_authority(second.pubkey(), pool);
+    let res = process(&mut context, &[ix], &[&second]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    assert_eq!(
+        get::<Pool>(&mut context, pool).await.authority,
+        authority.pubkey()
+    );
+}
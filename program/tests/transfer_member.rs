@@ -0,0 +1,139 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+struct Setup {
+    context: ProgramTestContext,
+    member: Keypair,
+    pool: Pubkey,
+}
+
+/// Sets up a member of a pool, with any other accounts added for the pool.
+async fn setup(add_accounts: impl FnOnce(&mut ProgramTest, Pubkey)) -> Setup {
+    let mut test = program_test();
+    let member = add_signer(&mut test);
+    let pool = add_pool(&mut test, &pool(Pubkey::new_unique()));
+    add_member(&mut test, member.pubkey(), pool, 7, 100, 300);
+    add_accounts(&mut test, pool);
+    Setup {
+        context: test.start_with_context().await,
+        member,
+        pool,
+    }
+}
+
+async fn lamports(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    context.banks_client.get_balance(address).await.unwrap()
+}
+
+#[tokio::test]
+async fn transfer_member_moves_the_record_and_closes_the_old_account() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(|_, _| {}).await;
+    let new_authority = Pubkey::new_unique();
+    let old_member = member_pda(member.pubkey(), pool).0;
+    let new_member = member_pda(new_authority, pool).0;
+    let signer_lamports = lamports(&mut context, member.pubkey()).await;
+
+    let ix = transfer_member(member.pubkey(), pool, new_authority);
+    process(&mut context, &[ix], &[&member]).await.unwrap();
+
+    // the record keeps its id and balances under the new authority
+    let state = get::<Member>(&mut context, new_member).await;
+    assert_eq!(
+        state,
+        Member {
+            id: 7,
+            pool,
+            authority: new_authority,
+            balance: 100,
+            total_balance: 300,
+        }
+    );
+
+    // the old account is closed, refunding the rent paid for the new one
+    assert!(context
+        .banks_client
+        .get_account(old_member)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        lamports(&mut context, member.pubkey()).await,
+        signer_lamports
+    );
+}
+
+#[tokio::test]
+async fn transfer_member_rejects_an_existing_member() {
+    // the new authority already joined the pool
+    let new_authority = Pubkey::new_unique();
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(|test, pool| {
+        add_member(test, new_authority, pool, 8, 0, 0);
+    })
+    .await;
+    let ix = transfer_member(member.pubkey(), pool, new_authority);
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, ProgramError::AccountAlreadyInitialized);
+    let state = get::<Member>(&mut context, member_pda(new_authority, pool).0).await;
+    assert_eq!((state.id, state.balance), (8, 0));
+    let state = get::<Member>(&mut context, member_pda(member.pubkey(), pool).0).await;
+    assert_eq!((state.id, state.balance), (7, 100));
+}
+
+#[tokio::test]
+async fn transfer_member_rejects_other_signers() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(|_, _| {}).await;
+    let signer = Keypair::new();
+    let mut ix = transfer_member(signer.pubkey(), pool, signer.pubkey());
+    ix.accounts[2].pubkey = member_pda(member.pubkey(), pool).0;
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn transfer_member_rejects_accounts_of_other_pools() {
+    let other_pool_authority = Pubkey::new_unique();
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(|test, _| {
+        add_pool(test, &common::pool(other_pool_authority));
+    })
+    .await;
+    let other_pool = pool_pda(other_pool_authority).0;
+    let new_authority = Pubkey::new_unique();
+    let mut ix = transfer_member(member.pubkey(), other_pool, new_authority);
+    ix.accounts[2].pubkey = member_pda(member.pubkey(), pool).0;
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn transfer_member_rejects_a_new_account_at_other_seeds() {
+    let Setup {
+        mut context,
+        member,
+        pool,
+    } = setup(|_, _| {}).await;
+    let mut ix = transfer_member(member.pubkey(), pool, Pubkey::new_unique());
+    ix.accounts[3].pubkey = member_pda(Pubkey::new_unique(), pool).0;
+    let res = process(&mut context, &[ix], &[&member]).await;
+    assert_error(res, ProgramError::InvalidSeeds);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+/// The unix timestamp the tests start at.
+const NOW: i64 = 1_700_000_000;
+
+async fn setup(commission: u64) -> (ProgramTestContext, Keypair, Pubkey) {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let pool = add_pool(
+        &mut test,
+        &Pool {
+            commission,
+            ..pool(authority.pubkey())
+        },
+    );
+    let mut context = test.start_with_context().await;
+    set_time(&mut context, NOW).await;
+    (context, authority, pool)
+}
+
+async fn commission(context: &mut ProgramTestContext, pool: Pubkey) -> (u64, u64, i64) {
+    let pool = get::<Pool>(context, pool).await;
+    (
+        pool.commission,
+        pool.pending_commission,
+        pool.commission_effective_at,
+    )
+}
+
+#[tokio::test]
+async fn update_commission_applies_decreases_immediately() {
+    let (mut context, authority, pool) = setup(10).await;
+    let ix = update_commission(authority.pubkey(), pool, 5);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(commission(&mut context, pool).await, (5, 0, 0));
+}
+
+#[tokio::test]
+async fn update_commission_timelocks_increases() {
+    let (mut context, authority, pool) = setup(5).await;
+    let ix = update_commission(authority.pubkey(), pool, 10);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let effective_at = NOW + COMMISSION_TIMELOCK;
+    assert_eq!(commission(&mut context, pool).await, (5, 10, effective_at));
+
+    // read as pending until the timelock elapses
+    let state = get::<Pool>(&mut context, pool).await;
+    assert_eq!(state.commission_at(effective_at - 1), 5);
+    assert_eq!(state.commission_at(effective_at), 10);
+
+    // a newer increase replaces it and restarts the timelock
+    set_time(&mut context, NOW + 100).await;
+    let ix = update_commission(authority.pubkey(), pool, 20);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let effective_at = NOW + 100 + COMMISSION_TIMELOCK;
+    assert_eq!(commission(&mut context, pool).await, (5, 20, effective_at));
+
+    // applied once the timelock elapses
+    set_time(&mut context, effective_at).await;
+    let ix = update_commission(authority.pubkey(), pool, 20);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(commission(&mut context, pool).await, (20, 0, 0));
+}
+
+#[tokio::test]
+async fn update_commission_decreases_cancel_pending_increases() {
+    let (mut context, authority, pool) = setup(5).await;
+    let ix = update_commission(authority.pubkey(), pool, 10);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    let ix = update_commission(authority.pubkey(), pool, 3);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(commission(&mut context, pool).await, (3, 0, 0));
+}
+
+#[tokio::test]
+async fn update_commission_applies_elapsed_increases_first() {
+    let (mut context, authority, pool) = setup(5).await;
+    let ix = update_commission(authority.pubkey(), pool, 10);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+
+    // 8 is a decrease from the 10 now in effect, so it applies immediately
+    set_time(&mut context, NOW + COMMISSION_TIMELOCK).await;
+    let ix = update_commission(authority.pubkey(), pool, 8);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(commission(&mut context, pool).await, (8, 0, 0));
+}
+
+#[tokio::test]
+async fn update_commission_is_bounded() {
+    let (mut context, authority, pool) = setup(5).await;
+    let ix = update_commission(authority.pubkey(), pool, MAX_COMMISSION + 1);
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, PoolError::CommissionTooLarge);
+    assert_eq!(commission(&mut context, pool).await, (5, 0, 0));
+
+    let ix = update_commission(authority.pubkey(), pool, MAX_COMMISSION);
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(
+        commission(&mut context, pool).await,
+        (5, MAX_COMMISSION, NOW + COMMISSION_TIMELOCK)
+    );
+}
+
+#[tokio::test]
+async fn update_commission_rejects_other_signers() {
+    let (mut context, _, pool) = setup(5).await;
+    let signer = Keypair::new();
+    let ix = update_commission(signer.pubkey(), pool, 0);
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    assert_eq!(commission(&mut context, pool).await, (5, 0, 0));
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::*;
+use ore_pool_api::prelude::*;
+use solana_program_test::*;
+use solana_sdk::signature::{Keypair, Signer};
+use steel::*;
+
+async fn setup() -> (ProgramTestContext, Keypair, Pubkey) {
+    let mut test = program_test();
+    let authority = add_signer(&mut test);
+    let pool = add_pool(&mut test, &pool(authority.pubkey()));
+    (test.start_with_context().await, authority, pool)
+}
+
+fn url(pool: &Pool) -> &str {
+    let len = pool
+        .url
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(pool.url.len());
+    std::str::from_utf8(&pool.url[..len]).unwrap()
+}
+
+#[tokio::test]
+async fn update_pool_sets_the_url() {
+    let (mut context, authority, pool) = setup().await;
+    let ix = update_pool(
+        authority.pubkey(),
+        pool,
+        "https://pool.ore.supply".to_string(),
+    )
+    .unwrap();
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(
+        url(&get::<Pool>(&mut context, pool).await),
+        "https://pool.ore.supply"
+    );
+
+    // a shorter url is padded over the previous one
+    let ix = update_pool(authority.pubkey(), pool, "https://ore.pool".to_string()).unwrap();
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(
+        url(&get::<Pool>(&mut context, pool).await),
+        "https://ore.pool"
+    );
+
+    // the longest url that fits
+    let ix = update_pool(authority.pubkey(), pool, "a".repeat(128)).unwrap();
+    process(&mut context, &[ix], &[&authority]).await.unwrap();
+    assert_eq!(url(&get::<Pool>(&mut context, pool).await), "a".repeat(128));
+}
+
+#[tokio::test]
+async fn update_pool_rejects_other_signers() {
+    let (mut context, _, pool) = setup().await;
+    let signer = Keypair::new();
+    let ix = update_pool(signer.pubkey(), pool, "https://evil.pool".to_string()).unwrap();
+    let res = process(&mut context, &[ix], &[&signer]).await;
+    assert_error(res, ProgramError::InvalidAccountData);
+    assert_eq!(url(&get::<Pool>(&mut context, pool).await), "");
+}
+
+#[tokio::test]
+async fn update_pool_rejects_malformed_data() {
+    let (mut context, authority, pool) = setup().await;
+    let mut ix = update_pool(authority.pubkey(), pool, "https://ore.pool".to_string()).unwrap();
+    ix.data.pop();
+    let res = process(&mut context, &[ix], &[&authority]).await;
+    assert_error(res, ProgramError::InvalidInstructionData);
+}
+
+#[test]
+fn update_pool_rejects_urls_over_128_bytes() {
+    let res = update_pool(Pubkey::new_unique(), Pubkey::new_unique(), "a".repeat(129));
+    assert!(matches!(res, Err(ApiError::UrlTooLarge)));
+}
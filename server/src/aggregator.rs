@@ -1,12 +1,16 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
+use arc_swap::ArcSwap;
 use ore_api::{
     consts::{BUS_ADDRESSES, BUS_COUNT},
     state::Bus,
 };
-use ore_pool_types::Challenge;
+use ore_pool_api::{
+    merkle::{contribution_leaf, MerkleTree},
+    state::Pool,
+};
+use ore_pool_types::{Block, BlockContribution, BlockWinner, Challenge};
 use rand::Rng;
-use sha3::{Digest, Sha3_256};
 use solana_sdk::{pubkey::Pubkey, signer::Signer};
 use steel::AccountDeserialize;
 
@@ -17,33 +21,58 @@ use crate::{
     database,
     error::Error,
     operator::Operator,
+    reward_policy::{self, RewardPolicy},
+    round::Round,
     tx,
 };
 
 const MAX_DIFFICULTY: u32 = 22;
-const MAX_SCORE: u64 = 2u64.pow(MAX_DIFFICULTY);
+pub const MAX_SCORE: u64 = 2u64.pow(MAX_DIFFICULTY);
+
+/// The number of challenges kept in the attribution window.
+pub const ATTRIBUTION_FILTER_SIZE: u8 = 15 + 1;
 
 /// Aggregates contributions from the pool members.
 pub struct Aggregator {
     /// The current challenge.
     pub current_challenge: Challenge,
 
+    /// The challenge submitted before the current one, to tell stale contributions apart.
+    pub previous_challenge: Option<Challenge>,
+
     /// The set of contributions for attribution.
+    /// The current challenge is filled in from the round once sealed.
     pub contributions: Contributions,
 
-    /// The number of workers that have been approved for the current challenge.
+    /// The contributions to the current challenge, inserted by the handlers.
+    pub round: Arc<ArcSwap<Round>>,
+
+    /// The number of member ids to partition the nonce space by for the current challenge.
+    /// Includes the ids of members that have since left the pool.
     pub num_members: u64,
 
     /// The set of recent mining events.
     pub recent_events: RecentEvents,
+
+    /// Splits the mining rewards between members.
+    pub reward_policy: Box<dyn RewardPolicy>,
+
+    /// Publishes each new challenge to the open challenge streams.
+    pub challenge_tx: tokio::sync::watch::Sender<ChallengeUpdate>,
+}
+
+/// A new challenge along with the number of members to partition its nonce space by.
+#[derive(Clone, Copy, Debug)]
+pub struct ChallengeUpdate {
+    pub challenge: Challenge,
+    pub num_members: u64,
 }
 
 pub async fn process_contributions(
     aggregator: &tokio::sync::RwLock<Aggregator>,
     operator: &Operator,
-    rx: &mut tokio::sync::mpsc::UnboundedReceiver<Contribution>,
 ) -> Result<(), Error> {
-    // outer loop for new challenges
+    // loop for new challenges
     loop {
         let timer = tokio::time::Instant::now();
         let cutoff_time = {
@@ -62,58 +91,17 @@ pub async fn process_contributions(
                 }
             }
         };
-        let mut remaining_time = cutoff_time.saturating_sub(timer.elapsed().as_secs());
-        // inner loop to process contributions until cutoff time
-        while remaining_time > 0 {
-            // race the next contribution against remaining time
-            match tokio::time::timeout(tokio::time::Duration::from_secs(remaining_time), rx.recv())
-                .await
-            {
-                Ok(Some(mut contribution)) => {
-                    {
-                        let mut aggregator = aggregator.write().await;
-                        let _ = aggregator.insert(&mut contribution);
-                    }
-                    // recalculate the remaining time after processing the contribution
-                    remaining_time = cutoff_time.saturating_sub(timer.elapsed().as_secs());
-                }
-                Ok(None) => {
-                    // if the receiver is closed, exit server
-                    return Err(Error::Internal("contribution channel closed".to_string()));
-                }
-                Err(_) => {
-                    // timeout expired, meaning cutoff time has been reached
-                    break;
-                }
-            }
-        }
-        // at this point, the cutoff time has been reached
-        let total_score = {
-            let mut write = aggregator.write().await;
-            let contributions = write.get_current_contributions();
-            match contributions {
-                Ok(contributions) => contributions.total_score,
-                Err(err) => {
-                    log::error!("{:?}", err);
-                    0
-                }
-            }
-        };
-        if total_score > 0 {
-            // submit if contributions exist
-            let mut aggregator = aggregator.write().await;
-            if let Err(err) = aggregator.submit_and_reset(operator).await {
-                log::error!("{:?}", err);
-            }
-        } else {
-            // no contributions yet, wait for the first one to submit
-            if let Some(mut contribution) = rx.recv().await {
-                let mut aggregator = aggregator.write().await;
-                let _ = aggregator.insert(&mut contribution);
-                if let Err(err) = aggregator.submit_and_reset(operator).await {
-                    log::error!("{:?}", err);
-                }
-            }
+        // the handlers insert contributions into the round until cutoff time
+        let remaining_time = cutoff_time.saturating_sub(timer.elapsed().as_secs());
+        tokio::time::sleep(tokio::time::Duration::from_secs(remaining_time)).await;
+        // at this point, the cutoff time has been reached.
+        // if no contributions exist yet, wait for the first one to submit
+        let round = aggregator.read().await.round.load_full();
+        round.first_contribution().await;
+        let mut aggregator = aggregator.write().await;
+        if let Err(err) = aggregator.submit_and_reset(operator).await {
+            log::error!("{:?}", err);
+            aggregator.reopen_round();
         }
     }
 }
@@ -122,6 +110,13 @@ impl Aggregator {
     pub async fn new(operator: &Operator) -> Result<Self, Error> {
         // fetch accounts
         let pool = operator.get_pool().await?;
+        if pool.authority.ne(&operator.keypair.pubkey()) {
+            return Err(Error::Internal(format!(
+                "operator keypair is not the pool authority: {}",
+                pool.authority
+            )));
+        }
+        log::info!("operator commission: {}", pool.commission);
         let proof = operator.get_proof().await?;
         log::info!("proof: {:?}", proof);
         let cutoff_time = operator.get_cutoff(&proof).await?;
@@ -133,109 +128,94 @@ impl Aggregator {
             cutoff_time,
         };
 
+        // restore from the last checkpoint
+        let (mut contributions, recent_events) = match Self::restore(operator).await {
+            Ok(Some(snapshot)) => {
+                log::info!("restored aggregator from snapshot");
+                snapshot
+            }
+            Ok(None) => (
+                Contributions::new(ATTRIBUTION_FILTER_SIZE),
+                RecentEvents::new(15),
+            ),
+            Err(err) => {
+                log::error!("failed to restore aggregator snapshot: {:?}", err);
+                (
+                    Contributions::new(ATTRIBUTION_FILTER_SIZE),
+                    RecentEvents::new(15),
+                )
+            }
+        };
+        if !contributions
+            .miners
+            .contains_key(&(challenge.lash_hash_at as u64))
+        {
+            contributions.insert(challenge.lash_hash_at as u64);
+        }
+        let round = Round::new(
+            challenge,
+            None,
+            pool.next_member_id,
+            contributions.miners.get(&(challenge.lash_hash_at as u64)),
+        );
+
+        // restore the state of the reward policy, as recorded with the last mining event
+        let mut reward_policy = reward_policy::from_env()?;
+        let db_client = operator.db_client.get().await?;
+        if let Some(state) = database::read_reward_policy_state(
+            &db_client,
+            &operator.pool_address(),
+            reward_policy.name(),
+        )
+        .await?
+        {
+            reward_policy.restore(state.as_slice())?;
+            log::info!("restored {} reward policy state", reward_policy.name());
+        }
+
         // build self
-        let mut contributions = Contributions::new(15 + 1);
-        contributions.insert(challenge.lash_hash_at as u64);
         let aggregator = Aggregator {
             current_challenge: challenge,
+            previous_challenge: None,
             contributions,
-            num_members: pool.last_total_members,
-            recent_events: RecentEvents::new(15),
+            round: Arc::new(ArcSwap::from_pointee(round)),
+            num_members: pool.next_member_id,
+            recent_events,
+            reward_policy,
+            challenge_tx: tokio::sync::watch::channel(ChallengeUpdate {
+                challenge,
+                num_members: pool.next_member_id,
+            })
+            .0,
         };
         Ok(aggregator)
     }
 
-    fn insert(&mut self, contribution: &mut Contribution) -> Result<(), Error> {
-        let challenge = &self.current_challenge.clone();
-        let solution = &contribution.solution;
-
-        // normalize contribution score
-        let normalized_score = contribution.score.min(MAX_SCORE);
-        contribution.score = normalized_score;
-
-        // get current contributions
-        let contributions = self.get_current_contributions()?;
-
-        // validate solution against current challenge
-        if !drillx::is_valid_digest(&challenge.challenge, &solution.n, &solution.d) {
-            log::error!("invalid solution");
-            return Err(Error::Internal("invalid solution".to_string()));
-        }
+    /// writes the contributions window and recent events to durable storage,
+    /// so that they survive a restart.
+    /// the reward policy state is recorded with each mining event instead.
+    pub async fn checkpoint(&mut self, operator: &Operator) -> Result<(), Error> {
+        // include the open round
+        self.sync_round();
+        let snapshot = bincode::serialize(&(&self.contributions, &self.recent_events))?;
+        let db_client = operator.db_client.get().await?;
+        database::write_snapshot(&db_client, &operator.pool_address(), snapshot.as_slice()).await
+    }
 
-        // insert
-        let insert = contributions.contributions.take(contribution);
-        match insert {
-            Some(prev) => {
-                // update if the new contribution score is larger
-                if contribution.score.gt(&prev.score) {
-                    // insert new contribution
-                    contributions.contributions.insert(*contribution);
-
-                    // build contender (to be compared to winner)
-                    let difficulty = contribution.solution.to_hash().difficulty();
-                    let contender = Winner {
-                        solution: contribution.solution,
-                        difficulty,
-                    };
-                    log::info!(
-                        "updated contribution: {:?} {}",
-                        contribution.member,
-                        difficulty
-                    );
-
-                    // decrement previous score
-                    contributions.total_score -= prev.score;
-
-                    // increment new score
-                    contributions.total_score += contribution.score;
-
-                    // update winner
-                    match contributions.winner {
-                        Some(winner) => {
-                            if difficulty > winner.difficulty {
-                                contributions.winner = Some(contender);
-                            }
-                        }
-                        None => contributions.winner = Some(contender),
-                    }
-                } else {
-                    // reinsert previous contribution
-                    // as the new contribution did not improve the score
-                    contributions.contributions.insert(prev);
-                }
-                Ok(())
-            }
-            None => {
-                // insert contribution
-                contributions.contributions.insert(*contribution);
-
-                // build contender (to be compared to winner)
-                let difficulty = contribution.solution.to_hash().difficulty();
-                let contender = Winner {
-                    solution: contribution.solution,
-                    difficulty,
-                };
-                log::info!("new contribution: {:?} {}", contribution.member, difficulty);
-
-                // increment score
-                contributions.total_score += contribution.score;
-
-                // update winner
-                match contributions.winner {
-                    Some(winner) => {
-                        if difficulty > winner.difficulty {
-                            contributions.winner = Some(contender);
-                        }
-                    }
-                    None => contributions.winner = Some(contender),
-                }
-                Ok(())
-            }
+    /// reads the contributions window and recent events from the last checkpoint, if any
+    async fn restore(operator: &Operator) -> Result<Option<(Contributions, RecentEvents)>, Error> {
+        let db_client = operator.db_client.get().await?;
+        let snapshot = database::read_snapshot(&db_client, &operator.pool_address()).await?;
+        match snapshot {
+            Some(snapshot) => Ok(Some(bincode::deserialize(snapshot.as_slice())?)),
+            None => Ok(None),
         }
     }
 
-    // TODO Publish block to S3
     async fn submit_and_reset(&mut self, operator: &Operator) -> Result<(), Error> {
+        // stop accepting contributions to the current challenge
+        self.close_round().await;
+
         // check if reset is needed
         // this may happen if a solution is landed on chain
         // but a subsequent application error is thrown before resetting
@@ -250,19 +230,43 @@ impl Aggregator {
         let winner = self.winner()?;
         log::info!("winner: {:?}", winner);
         let best_solution = winner.solution;
-        let attestation = self.attestation()?;
+        let (attestation, contributions) = self.attestation()?;
+
+        // persist leaves so members can prove their contributions were counted
+        let leaves: Vec<(Pubkey, [u8; 16], [u8; 8])> = contributions
+            .iter()
+            .map(|contribution| {
+                (
+                    contribution.member,
+                    contribution.solution.d,
+                    contribution.solution.n,
+                )
+            })
+            .collect();
+        let mut db_client = operator.db_client.get().await?;
+        database::write_attestation_leaves(
+            &mut db_client,
+            &operator.pool_address(),
+            self.current_challenge.lash_hash_at,
+            leaves.as_slice(),
+        )
+        .await?;
 
         // derive accounts for instructions
-        let authority = &operator.keypair.pubkey();
-        let (pool_address, _) = ore_pool_api::state::pool_pda(*authority);
+        let pool_address = operator.pool_address();
         let (pool_proof_address, _) = ore_pool_api::state::pool_proof_pda(pool_address);
         let bus = self.find_bus(operator).await?;
 
         // build instructions
         let auth_ix = ore_api::sdk::auth(pool_proof_address);
         let reset_ix = ore_api::sdk::reset(operator.keypair.pubkey());
-        let submit_ix =
-            ore_pool_api::sdk::submit(operator.keypair.pubkey(), best_solution, attestation, bus);
+        let submit_ix = ore_pool_api::sdk::submit(
+            operator.keypair.pubkey(),
+            pool_address,
+            best_solution,
+            attestation,
+            bus,
+        );
         let sig = tx::submit::submit_instructions(
             &operator.keypair,
             &operator.rpc_client,
@@ -274,6 +278,32 @@ impl Aggregator {
         .await?;
         log::info!("{:?}", sig);
 
+        // publish block
+        // failures are logged, as the submission has already landed
+        if let Some(publisher) = operator.block_publisher.as_ref() {
+            let block = Block {
+                challenge: self.current_challenge,
+                contributions: contributions
+                    .iter()
+                    .map(|contribution| BlockContribution {
+                        member: contribution.member,
+                        score: contribution.score,
+                        solution: contribution.solution,
+                    })
+                    .collect(),
+                winner: BlockWinner {
+                    solution: winner.solution,
+                    difficulty: winner.difficulty,
+                },
+                attestation,
+                signature: sig,
+            };
+            match publisher.publish(&block).await {
+                Ok(location) => log::info!("published block: {}", location),
+                Err(err) => log::error!("failed to publish block: {:?}", err),
+            }
+        }
+
         // reset
         self.reset(operator).await?;
         Ok(())
@@ -286,6 +316,29 @@ impl Aggregator {
     ) -> Result<(), Error> {
         log::info!("{:?}", event);
 
+        // Include the open round, for reward policies that count it
+        self.sync_round();
+
+        // Skip events that were already distributed,
+        // the writes below are rolled back with the processed marker on failure
+        let mut db_client = operator.db_client.get().await?;
+        let transaction = db_client.transaction().await?;
+        if !database::write_processed_event(
+            &transaction,
+            &operator.pool_address(),
+            &event.signature,
+            event.mine_event.last_hash_at,
+        )
+        .await?
+        {
+            log::info!(
+                "mining event already distributed: {} {}",
+                event.mine_event.last_hash_at,
+                event.signature
+            );
+            return Ok(());
+        }
+
         // Calculate pool
         let net_pool_rewards = event
             .mine_event
@@ -294,24 +347,48 @@ impl Aggregator {
             .unwrap();
 
         // Compute operator rewards
+        let pool = operator.get_pool().await?;
         let operator_rewards = self.rewards_distribution_operator(
             operator.keypair.pubkey(),
+            &pool,
+            event.mine_event.last_hash_at,
             net_pool_rewards,
-            operator.operator_commission,
         );
 
         // Compute miner rewards
-        let mut rewards_distribution =
-            self.rewards_distribution(net_pool_rewards, operator_rewards.1);
+        let miner_rewards = net_pool_rewards.checked_sub(operator_rewards.1).unwrap();
+        let mut rewards_distribution = self.rewards_distribution(event, miner_rewards);
 
         println!("rewards_distribution: {:?}", rewards_distribution);
 
+        // Record the distribution in the reward policy state,
+        // persisted with the distribution and applied once it commits
+        let reward_policy_state = self.reward_policy.record(
+            &self.contributions,
+            event,
+            miner_rewards,
+            rewards_distribution.as_slice(),
+        )?;
+        if let Some(state) = reward_policy_state.as_ref() {
+            database::write_reward_policy_state(
+                &transaction,
+                &operator.pool_address(),
+                self.reward_policy.name(),
+                state.as_slice(),
+            )
+            .await?;
+        }
+
         // Collect all rewards
         rewards_distribution.push(operator_rewards);
 
         // Write rewards to db
-        let mut db_client = operator.db_client.get().await?;
-        database::update_member_balances(&mut db_client, rewards_distribution.clone()).await?;
+        database::update_member_balances(
+            &transaction,
+            &operator.pool_address(),
+            rewards_distribution.clone(),
+        )
+        .await?;
 
         // Get best member scores for this event
         let member_scores = if let Some(miner_contributions) = self
@@ -334,45 +411,58 @@ impl Aggregator {
         let mut event = event.clone();
         event.member_scores = member_scores;
         event.member_rewards = HashMap::from_iter(rewards_distribution);
+
+        // Write record to db for the event history
+        database::write_mining_event(&transaction, &operator.pool_address(), &event).await?;
+        transaction.commit().await?;
+        if let Some(state) = reward_policy_state {
+            if let Err(err) = self.reward_policy.restore(state.as_slice()) {
+                log::error!("{:?}", err);
+            }
+        }
         self.recent_events
             .insert(event.mine_event.last_hash_at as u64, event);
 
         Ok(())
     }
 
+    /// distributes the rewards of a past mining event,
+    /// against the attribution window persisted at the time
+    pub async fn replay_rewards(
+        &mut self,
+        operator: &Operator,
+        event: &PoolMiningEvent,
+        contributions: Contributions,
+    ) -> Result<(), Error> {
+        // swap the live window back in once distributed
+        let live = std::mem::replace(&mut self.contributions, contributions);
+        let res = self.distribute_rewards(operator, event).await;
+        self.contributions = live;
+        res
+    }
+
     fn rewards_distribution(
         &mut self,
-        net_pool_rewards: u64,
-        operator_rewards: u64,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
     ) -> Vec<(Pubkey, u64)> {
-        // Get attributed scores
-        let contributions = &mut self.contributions;
-        let (total_score, scores) = contributions.scores();
-
-        // Calculate total miner rewards
-        let miner_rewards = net_pool_rewards.checked_sub(operator_rewards).unwrap();
         log::info!("total miner rewards: {}", miner_rewards);
 
         // compute member split
-        scores
-            .iter()
-            .map(|(member, member_score)| {
-                let member_rewards = (miner_rewards as u128)
-                    .checked_mul(*member_score as u128)
-                    .unwrap()
-                    .checked_div(total_score as u128)
-                    .unwrap_or(0) as u64;
-                (*member, member_rewards)
-            })
-            .collect()
+        self.reward_policy
+            .distribute(&mut self.contributions, event, miner_rewards)
     }
 
+    /// computes the operator commission
+    /// from the rate recorded on chain at the time of the mining event
     fn rewards_distribution_operator(
         &self,
         pool_authority: Pubkey,
+        pool: &Pool,
+        timestamp: i64,
         net_pool_rewards: u64,
-        operator_commission: u64,
     ) -> (Pubkey, u64) {
+        let operator_commission = pool.commission_at(timestamp);
         let operator_rewards = (net_pool_rewards as u128)
             .saturating_mul(operator_commission as u128)
             .saturating_div(100) as u64;
@@ -398,32 +488,29 @@ impl Aggregator {
         Ok(top_bus)
     }
 
-    fn attestation(&mut self) -> Result<[u8; 32], Error> {
-        let mut hasher = Sha3_256::new();
+    /// builds the attestation as a merkle root over the current contributions.
+    /// also returns the contributions in tree order.
+    fn attestation(&mut self) -> Result<([u8; 32], Vec<Contribution>), Error> {
         let contributions = self.get_current_contributions()?;
         let num_contributions = contributions.contributions.len();
         log::info!("num contributions: {}", num_contributions);
-        for contribution in contributions.contributions.iter() {
-            let hex_string: String =
-                contribution
-                    .solution
-                    .d
-                    .iter()
-                    .fold(String::new(), |mut acc, byte| {
-                        acc.push_str(&format!("{:02x}", byte));
-                        acc
-                    });
-            let line = format!(
-                "{} {} {}\n",
-                contribution.member,
-                hex_string,
-                u64::from_le_bytes(contribution.solution.n)
-            );
-            hasher.update(&line);
-        }
-        let mut attestation: [u8; 32] = [0; 32];
-        attestation.copy_from_slice(&hasher.finalize()[..]);
-        Ok(attestation)
+        let mut contributions: Vec<Contribution> =
+            contributions.contributions.iter().copied().collect();
+        // sort for a deterministic tree
+        contributions.sort_by_key(|contribution| contribution.member);
+        let tree = MerkleTree::new(
+            contributions
+                .iter()
+                .map(|contribution| {
+                    contribution_leaf(
+                        &contribution.member,
+                        &contribution.solution.d,
+                        &contribution.solution.n,
+                    )
+                })
+                .collect(),
+        );
+        Ok((tree.root(), contributions))
     }
 
     fn get_current_contributions(&mut self) -> Result<&mut MinerContributions, Error> {
@@ -452,10 +539,59 @@ impl Aggregator {
 
         // reset accumulators
         let pool = operator.get_pool().await?;
-        self.num_members = pool.last_total_members;
+        self.num_members = pool.next_member_id;
+
+        // open a round for the new challenge
+        self.open_round();
+
+        // publish the challenge once its members are known
+        self.challenge_tx.send_replace(ChallengeUpdate {
+            challenge: self.current_challenge,
+            num_members: self.num_members,
+        });
         Ok(())
     }
 
+    /// installs a round for the current challenge,
+    /// seeded with the contributions already in the window
+    fn open_round(&mut self) {
+        let last_hash_at = self.current_challenge.lash_hash_at as u64;
+        let round = Round::new(
+            self.current_challenge,
+            self.previous_challenge,
+            self.num_members,
+            self.contributions.miners.get(&last_hash_at),
+        );
+        self.round.store(Arc::new(round));
+    }
+
+    /// accepts contributions again after a failed submission,
+    /// opening a new round if the challenge moved on in the meantime
+    fn reopen_round(&mut self) {
+        let round = self.round.load_full();
+        if round.challenge.lash_hash_at == self.current_challenge.lash_hash_at {
+            round.unseal();
+        } else {
+            self.open_round();
+        }
+    }
+
+    /// seals the round and moves its contributions into the window
+    async fn close_round(&mut self) {
+        let round = self.round.load_full();
+        round.seal().await;
+        self.sync_round();
+    }
+
+    /// copies the contributions of the round into the window
+    fn sync_round(&mut self) {
+        let round = self.round.load_full();
+        let last_hash_at = round.challenge.lash_hash_at as u64;
+        if let Some(contributions) = self.contributions.miners.get_mut(&last_hash_at) {
+            *contributions = round.snapshot();
+        }
+    }
+
     fn winner(&mut self) -> Result<Winner, Error> {
         let contributions = self.get_current_contributions()?;
         let winner = contributions.winner;
@@ -478,6 +614,7 @@ impl Aggregator {
             if proof.last_hash_at != last_hash_at {
                 let cutoff_time = operator.get_cutoff(&proof).await?;
                 let min_difficulty = operator.min_difficulty().await?;
+                self.previous_challenge = Some(self.current_challenge);
                 self.current_challenge.challenge = proof.challenge;
                 self.current_challenge.lash_hash_at = proof.last_hash_at;
                 self.current_challenge.min_difficulty = min_difficulty;
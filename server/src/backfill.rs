@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use solana_sdk::signature::Signature;
+
+use crate::{
+    aggregator::{Aggregator, ATTRIBUTION_FILTER_SIZE},
+    contributions::Contributions,
+    database,
+    error::Error,
+    listener,
+    operator::Operator,
+};
+
+/// The age in seconds past which a mining event is left to the backfill.
+/// Newer events are still owned by the live pipeline,
+/// and their contributions may not be flushed to the database yet.
+const REPLAY_HORIZON: i64 = 5 * 60;
+
+/// Walks the transaction history of the pool proof since the last processed mining event,
+/// and replays any mining event that was missed, e.g. while the server was down.
+/// Returns the number of events replayed.
+pub async fn backfill(
+    operator: &Operator,
+    aggregator: &tokio::sync::RwLock<Aggregator>,
+) -> Result<usize, Error> {
+    // find the last processed signature behind the horizon,
+    // so events skipped as too recent are walked again on the next pass
+    let db_client = operator.db_client.get().await?;
+    let pool_address = operator.pool_address();
+    let horizon = crate::unix_timestamp() - REPLAY_HORIZON;
+    let Some(until) =
+        database::read_last_processed_signature(&db_client, &pool_address, horizon).await?
+    else {
+        // nothing to reconcile against yet
+        return Ok(0);
+    };
+
+    // collect every signature since, newest first
+    let statuses =
+        listener::signatures_until(&operator.rpc_client, &operator.proof_address(), Some(until))
+            .await?;
+
+    // replay unprocessed events, oldest first
+    let mut replayed = 0;
+    for status in statuses.into_iter().rev() {
+        if status.err.is_some() {
+            continue;
+        }
+        let signature = match Signature::from_str(status.signature.as_str()) {
+            Ok(signature) => signature,
+            Err(err) => {
+                log::error!("invalid signature: {} {:?}", status.signature, err);
+                continue;
+            }
+        };
+        // left unprocessed on error, to be retried on the next pass
+        let (slot, timestamp, mine_event) =
+            match listener::fetch_mine_event(operator, &signature, "backfill").await {
+                Ok(Some(event)) => event,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::error!("{} {:?}", signature, err);
+                    continue;
+                }
+            };
+        let last_hash_at = mine_event.last_hash_at;
+        if last_hash_at >= horizon {
+            continue;
+        }
+        if database::processed_event_exists(&db_client, &pool_address, last_hash_at).await? {
+            continue;
+        }
+        log::info!(
+            "replaying missed mining event: {} {}",
+            last_hash_at,
+            signature
+        );
+        let records = database::read_contribution_window(
+            &db_client,
+            &pool_address,
+            last_hash_at,
+            ATTRIBUTION_FILTER_SIZE as i64,
+        )
+        .await?;
+        let contributions = Contributions::from_records(ATTRIBUTION_FILTER_SIZE, &records);
+        let event = listener::pool_mining_event(signature, slot, timestamp, mine_event);
+        let mut aggregator = aggregator.write().await;
+        aggregator
+            .replay_rewards(operator, &event, contributions)
+            .await?;
+        replayed += 1;
+    }
+    Ok(replayed)
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use ore_pool_api::merkle::{balance_leaf, MerkleTree};
+use ore_pool_types::MemberBalanceProof;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::Error;
+
+/// How member balances are committed on-chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BalanceMode {
+    /// The operator attributes each member balance.
+    Attribute,
+
+    /// The operator posts a Merkle root of all member balances,
+    /// and members claim with a proof.
+    Merkle,
+}
+
+/// The Merkle tree of member lifetime earnings, as last posted on-chain.
+#[derive(Default)]
+pub struct BalanceTree {
+    /// The tree built from the member balances.
+    tree: MerkleTree,
+
+    /// The leaf index, id and committed balance of each member.
+    members: HashMap<Pubkey, (usize, u64, u64)>,
+}
+
+impl BalanceTree {
+    /// builds the tree from the authority, id and lifetime balance of each member
+    pub fn new(balances: Vec<(Pubkey, u64, u64)>) -> Self {
+        let leaves = balances
+            .iter()
+            .map(|(authority, id, total_balance)| balance_leaf(authority, *id, *total_balance))
+            .collect();
+        let members = balances
+            .into_iter()
+            .enumerate()
+            .map(|(index, (authority, id, total_balance))| (authority, (index, id, total_balance)))
+            .collect();
+        Self {
+            tree: MerkleTree::new(leaves),
+            members,
+        }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn proof(&self, authority: &Pubkey) -> Option<MemberBalanceProof> {
+        let (index, id, total_balance) = self.members.get(authority)?;
+        let proof = self.tree.proof(*index)?;
+        Some(MemberBalanceProof {
+            authority: *authority,
+            id: *id,
+            total_balance: *total_balance,
+            root: self.root(),
+            proof,
+        })
+    }
+}
+
+impl std::str::FromStr for BalanceMode {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "attribute" => Ok(BalanceMode::Attribute),
+            "merkle" => Ok(BalanceMode::Merkle),
+            _ => Err(Error::Internal(format!("invalid balance mode: {}", s))),
+        }
+    }
+}
@@ -2,9 +2,12 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 
 use drillx::Solution;
+use ore_pool_types::{ContributionRecord, ContributionStatus};
+use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 
+#[derive(Serialize, Deserialize)]
 pub struct Contributions {
     pub miners: HashMap<LastHashAt, MinerContributions>,
     attribution_filter: AttributionFilter,
@@ -18,16 +21,62 @@ impl Contributions {
         }
     }
 
+    /// rebuilds the window from persisted records,
+    /// keeping the best accepted contribution of each member per challenge
+    pub fn from_records(attribution_filter_size: u8, records: &[ContributionRecord]) -> Self {
+        let mut contributions = Self::new(attribution_filter_size);
+        let mut time_stamps: Vec<LastHashAt> = records
+            .iter()
+            .map(|record| record.last_hash_at as u64)
+            .collect();
+        time_stamps.sort();
+        time_stamps.dedup();
+        for ts in time_stamps {
+            contributions.insert(ts);
+        }
+        for record in records {
+            if record.status != ContributionStatus::Accepted {
+                continue;
+            }
+            let Some(miner_contributions) = contributions
+                .miners
+                .get_mut(&(record.last_hash_at as u64))
+            else {
+                continue;
+            };
+            let contribution = Contribution {
+                member: record.member,
+                score: record.score,
+                solution: Solution::new(record.digest, record.nonce),
+            };
+            let prev_score = miner_contributions
+                .contributions
+                .get(&contribution)
+                .map(|prev| prev.score);
+            match prev_score {
+                Some(prev_score) if prev_score >= contribution.score => {}
+                prev_score => {
+                    miner_contributions.total_score -= prev_score.unwrap_or(0);
+                    miner_contributions.total_score += contribution.score;
+                    miner_contributions.contributions.replace(contribution);
+                }
+            }
+        }
+        contributions
+    }
+
     pub fn insert(&mut self, ts: LastHashAt) {
         let contributions = MinerContributions {
             contributions: HashSet::new(),
             winner: None,
             total_score: 0,
         };
-        if let Some(_) = self.miners.insert(ts, contributions) {
+        if self.miners.insert(ts, contributions).is_some() {
             log::error!("contributions at last-hash-at already exist: {}", ts);
         }
         self.attribution_filter.push(ts);
+        // drop contributions that fell out of the filter
+        self.filter();
     }
 
     pub fn scores(&mut self) -> (TotalScore, Vec<(Miner, u64)>) {
@@ -61,6 +110,7 @@ pub type Miner = Pubkey;
 /// miner lookup table
 /// challenge --> contribution
 pub type LastHashAt = u64;
+#[derive(Serialize, Deserialize)]
 pub struct MinerContributions {
     pub contributions: HashSet<Contribution>,
     pub winner: Option<Winner>,
@@ -71,6 +121,7 @@ pub struct MinerContributions {
 pub type TotalScore = u64;
 
 /// timestamps of last n challenges
+#[derive(Serialize, Deserialize)]
 pub struct AttributionFilter {
     /// total num elements in vec
     pub len: u8,
@@ -100,7 +151,7 @@ impl AttributionFilter {
 }
 
 /// Best hash to be submitted for the current challenge.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Winner {
     /// The winning solution.
     pub solution: Solution,
@@ -110,7 +161,7 @@ pub struct Winner {
 }
 
 /// A recorded contribution from a particular member of the pool.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Contribution {
     /// The member who submitted this solution.
     pub member: Pubkey,
@@ -137,6 +188,7 @@ impl Hash for Contribution {
 }
 
 /// Tracks recent mining events and rewards for each submission
+#[derive(Serialize, Deserialize)]
 pub struct RecentEvents {
     /// Maps last_hash_at timestamp to mining event data
     events: HashMap<LastHashAt, PoolMiningEvent>,
@@ -144,11 +196,12 @@ pub struct RecentEvents {
     max_events: usize,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PoolMiningEvent {
     pub signature: Signature,
     pub block: u64,
     pub timestamp: u64,
+    #[serde(with = "mine_event_bytes")]
     pub mine_event: ore_api::event::MineEvent,
     pub member_rewards: HashMap<Pubkey, u64>,
     pub member_scores: HashMap<Pubkey, u64>,
@@ -179,4 +232,19 @@ impl RecentEvents {
     pub fn get(&self, last_hash_at: LastHashAt) -> Option<&PoolMiningEvent> {
         self.events.get(&last_hash_at)
     }
-}
\ No newline at end of file
+}
+
+/// (De)serializes the mine event as its raw bytes, since it does not implement serde.
+mod mine_event_bytes {
+    use ore_api::event::MineEvent;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(event: &MineEvent, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytemuck::bytes_of(event))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MineEvent, D::Error> {
+        let bytes: Vec<u8> = Deserialize::deserialize(deserializer)?;
+        bytemuck::try_pod_read_unaligned(bytes.as_slice()).map_err(D::Error::custom)
+    }
+}
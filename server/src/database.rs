@@ -1,12 +1,17 @@
-use std::{env, str::FromStr, sync::Arc};
+use std::{collections::HashSet, env, str::FromStr, sync::Arc};
 
-use crate::{error::Error, operator::Operator, tx};
-use deadpool_postgres::{GenericClient, Object, Pool};
+use crate::{contributions::PoolMiningEvent, error::Error, operator::Operator, tx};
+use deadpool_postgres::{GenericClient, Object, Pool, Transaction};
 use futures::TryStreamExt;
 use futures_util::pin_mut;
-use ore_pool_api::state::member_pda;
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signer::Signer};
-use tokio_postgres::NoTls;
+use ore_pool_api::state::{member_pda, Member};
+use ore_pool_types::{
+    Ban, ContributionRecord, ContributionStatus, EventsQuery, PoolMemberMiningEvent,
+    PoolMiningEventRecord,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+use steel::AccountDeserialize;
+use tokio_postgres::{NoTls, Row};
 
 pub fn create_pool() -> Pool {
     let mut cfg = deadpool_postgres::Config::new();
@@ -17,49 +22,75 @@ pub fn create_pool() -> Pool {
 // when writing new balances
 // also sets the is-synced field to false
 // so that in the attribution loop we know which accounts
-// have been incremented in the db but not yet on-chain
+// have been incremented in the db but not yet on-chain.
+// runs in the transaction of the mining event being distributed
 pub async fn update_member_balances(
-    conn: &mut Object,
+    transaction: &Transaction<'_>,
+    pool_address: &Pubkey,
     increments: Vec<(Pubkey, u64)>,
 ) -> Result<(), Error> {
-    let transaction = conn.transaction().await?;
     for (address, increment) in increments.iter() {
         transaction
                 .execute(
-                    "UPDATE members SET total_balance = total_balance + $1, is_synced = false WHERE authority = $2",
-                    &[&(*increment as i64), &address.to_string()],
+                    "UPDATE members SET total_balance = total_balance + $1, is_synced = false WHERE pool_address = $2 AND authority = $3",
+                    &[&(*increment as i64), &pool_address.to_string(), &address.to_string()],
                 )
                 .await?;
     }
-    transaction.commit().await?;
     Ok(())
 }
 
+// marks a mining event as processed,
+// returning false if the signature or challenge was already processed
+pub async fn write_processed_event(
+    transaction: &Transaction<'_>,
+    pool_address: &Pubkey,
+    signature: &Signature,
+    last_hash_at: i64,
+) -> Result<bool, Error> {
+    let count = transaction
+        .execute(
+            "INSERT INTO processed_events (pool_address, signature, last_hash_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING",
+            &[&pool_address.to_string(), &signature.to_string(), &last_hash_at],
+        )
+        .await?;
+    Ok(count > 0)
+}
+
 // streams all records from db where is-synced is false
 // updates on-chain balances in batches and marks records in db as synced,
 // the on-chain attribution instruction is idempotent
 // so any failures here are recoverable
-const NUM_ATTRIBUTIONS_PER_TX: usize = 10;
+//
+// each attribution costs a member account key and 8 bytes of instruction data,
+// so this is bounded by the transaction size limit
+const NUM_ATTRIBUTIONS_PER_TX: usize = 18;
 pub async fn stream_members_attribution(
     conn: Arc<Object>,
     operator: Arc<Operator>,
 ) -> Result<(), Error> {
     // fetch count(*) to determine min buffer size
-    let count_query = "SELECT COUNT(*) FROM members WHERE is_synced = false";
-    let row = conn.query_one(count_query, &[]).await?;
+    let pool_address = operator.pool_address();
+    let count_query = "SELECT COUNT(*) FROM members WHERE pool_address = $1 AND is_synced = false";
+    let row = conn
+        .query_one(count_query, &[&pool_address.to_string()])
+        .await?;
     let record_count: i64 = row.try_get(0)?;
 
     // build stream of memebrs to be attributed
-    let stmt = "SELECT address, authority, total_balance FROM members WHERE is_synced = false";
-    let params: Vec<String> = vec![];
+    let stmt = "SELECT address, authority, total_balance, id FROM members WHERE pool_address = $1 AND is_synced = false";
+    let params: Vec<String> = vec![pool_address.to_string()];
     let stream = conn.query_raw(stmt, params).await?;
     pin_mut!(stream);
 
     // buffer stream for packing attributions transaction
     let signer = operator.keypair.pubkey();
     let buffer_size = NUM_ATTRIBUTIONS_PER_TX.min(record_count as usize);
-    let mut ix_buffer: Vec<Instruction> = Vec::with_capacity(buffer_size);
+    let mut attribution_buffer: Vec<(Pubkey, u64)> = Vec::with_capacity(buffer_size);
     let mut address_buffer: Vec<String> = Vec::with_capacity(buffer_size);
+    let mut id_buffer: Vec<i64> = Vec::with_capacity(buffer_size);
     let mut handles: Vec<tokio::task::JoinHandle<()>> = vec![];
     while let Some(row) = stream.try_next().await? {
         // parse row
@@ -67,26 +98,51 @@ pub async fn stream_members_attribution(
         let member_authority: String = row.try_get(1)?;
         let member_authority = Pubkey::from_str(member_authority.as_str())?;
         let total_balance: i64 = row.try_get(2)?;
+        let id: i64 = row.try_get(3)?;
 
-        // build instruction
-        let ix = ore_pool_api::sdk::attribute(signer, member_authority, total_balance as u64);
-        ix_buffer.push(ix);
+        // buffer attribution
+        attribution_buffer.push((member_authority, total_balance as u64));
         address_buffer.push(address);
+        id_buffer.push(id);
 
         // if buffer is full
-        if ix_buffer.len().eq(&buffer_size) {
+        if attribution_buffer.len().eq(&buffer_size) {
             let conn = conn.clone();
             let operator = operator.clone();
             let handle = tokio::spawn({
-                let ix_buffer = ix_buffer.clone();
+                let attribution_buffer = attribution_buffer.clone();
                 let address_buffer = address_buffer.clone();
+                let id_buffer = id_buffer.clone();
                 async move {
+                    let (attribution_buffer, address_buffer) = match reconcile_attributions(
+                        conn.as_ref(),
+                        operator.as_ref(),
+                        attribution_buffer,
+                        address_buffer,
+                        id_buffer,
+                    )
+                    .await
+                    {
+                        Ok(reconciled) => reconciled,
+                        Err(err) => {
+                            log::error!("attribution failure: {:?}", err);
+                            return;
+                        }
+                    };
+                    if attribution_buffer.is_empty() {
+                        return;
+                    }
+                    let ix = ore_pool_api::sdk::attribute_many(
+                        signer,
+                        pool_address,
+                        attribution_buffer.as_slice(),
+                    );
                     match tx::submit::submit_and_confirm_instructions(
                         &operator.keypair,
                         &operator.rpc_client,
                         &operator.jito_client,
-                        ix_buffer.as_slice(),
-                        60_000,
+                        &[ix],
+                        200_000,
                         2_000,
                     )
                     .await
@@ -110,7 +166,8 @@ pub async fn stream_members_attribution(
 
             // clear buffers
             address_buffer.clear();
-            ix_buffer.clear();
+            attribution_buffer.clear();
+            id_buffer.clear();
         }
     }
 
@@ -119,15 +176,720 @@ pub async fn stream_members_attribution(
     Ok(())
 }
 
+// checks a batch of attributions against the on-chain member accounts,
+// so that one record out of step with the chain does not fail the whole batch.
+// stale records are reset to their account and left out of the batch,
+// as are the records of closed accounts until the member leaves or is transferred
+async fn reconcile_attributions(
+    conn: &Object,
+    operator: &Operator,
+    attributions: Vec<(Pubkey, u64)>,
+    addresses: Vec<String>,
+    ids: Vec<i64>,
+) -> Result<(Vec<(Pubkey, u64)>, Vec<String>), Error> {
+    let member_addresses = addresses
+        .iter()
+        .map(|address| Pubkey::from_str(address.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let accounts = operator
+        .rpc_client
+        .get_multiple_accounts(member_addresses.as_slice())
+        .await?;
+    let mut reconciled_attributions = Vec::with_capacity(attributions.len());
+    let mut reconciled_addresses = Vec::with_capacity(addresses.len());
+    for (((attribution, address), id), account) in attributions
+        .into_iter()
+        .zip(addresses)
+        .zip(ids)
+        .zip(accounts)
+    {
+        let member = account
+            .as_ref()
+            .and_then(|account| Member::try_from_bytes(account.data.as_slice()).ok());
+        match member {
+            None => {
+                log::warn!("skipping attribution to closed member account: {}", address);
+            }
+            Some(member) if is_stale(id, attribution.1 as i64, member) => {
+                log::warn!(
+                    "resetting stale member record: {} {} {}",
+                    address,
+                    attribution.1,
+                    member.total_balance
+                );
+                reset_member(conn, &address, member).await?;
+            }
+            _ => {
+                reconciled_attributions.push(attribution);
+                reconciled_addresses.push(address);
+            }
+        }
+    }
+    Ok((reconciled_attributions, reconciled_addresses))
+}
+
+// whether a member record is out of step with its on-chain account.
+// a member that left and joined again has a new id and starts over from zero,
+// and the on-chain lifetime balance only moves with attribution,
+// so a record behind it was never told of the earnings,
+// e.g. a member transferred to a new authority
+pub fn is_stale(id: i64, total_balance: i64, member: &Member) -> bool {
+    id.ne(&(member.id as i64)) || total_balance < member.total_balance as i64
+}
+
+// resets a member record to its on-chain account
+pub async fn reset_member(conn: &Object, address: &String, member: &Member) -> Result<(), Error> {
+    conn.execute(
+        "UPDATE members SET id = $1, total_balance = $2, is_synced = true WHERE address = $3",
+        &[&(member.id as i64), &(member.total_balance as i64), address],
+    )
+    .await?;
+    Ok(())
+}
+
+// reads the id and lifetime balance of every member of the pool, ordered by id
+// for building the balance root
+pub async fn read_member_balances(
+    conn: &Object,
+    pool_address: &Pubkey,
+) -> Result<Vec<(Pubkey, u64, u64)>, Error> {
+    let rows = conn
+        .query(
+            "SELECT authority, id, total_balance FROM members WHERE pool_address = $1 ORDER BY id",
+            &[&pool_address.to_string()],
+        )
+        .await?;
+    let mut balances = Vec::with_capacity(rows.len());
+    for row in rows {
+        let authority: String = row.try_get(0)?;
+        let authority = Pubkey::from_str(authority.as_str())?;
+        let id: i64 = row.try_get(1)?;
+        let total_balance: i64 = row.try_get(2)?;
+        balances.push((authority, id as u64, total_balance as u64));
+    }
+    Ok(balances)
+}
+
+// persists the leaves of an attestation in tree order,
+// replacing any leaves previously written for the same challenge
+pub async fn write_attestation_leaves(
+    conn: &mut Object,
+    pool_address: &Pubkey,
+    last_hash_at: i64,
+    leaves: &[(Pubkey, [u8; 16], [u8; 8])],
+) -> Result<(), Error> {
+    let pool_address = pool_address.to_string();
+    let leaf_indices: Vec<i32> = (0..leaves.len() as i32).collect();
+    let members: Vec<String> = leaves.iter().map(|(m, _, _)| m.to_string()).collect();
+    let digests: Vec<Vec<u8>> = leaves.iter().map(|(_, d, _)| d.to_vec()).collect();
+    let nonces: Vec<Vec<u8>> = leaves.iter().map(|(_, _, n)| n.to_vec()).collect();
+    let transaction = conn.transaction().await?;
+    transaction
+        .execute(
+            "DELETE FROM attestation_leaves WHERE pool_address = $1 AND last_hash_at = $2",
+            &[&pool_address, &last_hash_at],
+        )
+        .await?;
+    transaction
+        .execute(
+            "INSERT INTO attestation_leaves (pool_address, last_hash_at, leaf_index, member, digest, nonce)
+            SELECT $1, $2, * FROM UNNEST($3::INT[], $4::VARCHAR[], $5::BYTEA[], $6::BYTEA[])",
+            &[
+                &pool_address,
+                &last_hash_at,
+                &leaf_indices,
+                &members,
+                &digests,
+                &nonces,
+            ],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+// reads the leaves of an attestation in tree order
+pub async fn read_attestation_leaves(
+    conn: &Object,
+    pool_address: &Pubkey,
+    last_hash_at: i64,
+) -> Result<Vec<(Pubkey, [u8; 16], [u8; 8])>, Error> {
+    let rows = conn
+        .query(
+            "SELECT member, digest, nonce FROM attestation_leaves
+            WHERE pool_address = $1 AND last_hash_at = $2
+            ORDER BY leaf_index",
+            &[&pool_address.to_string(), &last_hash_at],
+        )
+        .await?;
+    let mut leaves = Vec::with_capacity(rows.len());
+    for row in rows {
+        let member: String = row.try_get(0)?;
+        let member = Pubkey::from_str(member.as_str())?;
+        let digest: Vec<u8> = row.try_get(1)?;
+        let nonce: Vec<u8> = row.try_get(2)?;
+        leaves.push((
+            member,
+            digest.as_slice().try_into()?,
+            nonce.as_slice().try_into()?,
+        ));
+    }
+    Ok(leaves)
+}
+
+pub async fn write_contributions(
+    conn: &Object,
+    pool_address: &Pubkey,
+    records: &[ContributionRecord],
+) -> Result<(), Error> {
+    let members: Vec<String> = records.iter().map(|r| r.member.to_string()).collect();
+    let last_hash_ats: Vec<i64> = records.iter().map(|r| r.last_hash_at).collect();
+    let digests: Vec<Vec<u8>> = records.iter().map(|r| r.digest.to_vec()).collect();
+    let nonces: Vec<Vec<u8>> = records.iter().map(|r| r.nonce.to_vec()).collect();
+    let difficulties: Vec<i32> = records.iter().map(|r| r.difficulty as i32).collect();
+    let scores: Vec<i64> = records.iter().map(|r| r.score as i64).collect();
+    let statuses: Vec<&str> = records
+        .iter()
+        .map(|r| match r.status {
+            ContributionStatus::Accepted => "accepted",
+            ContributionStatus::Rejected => "rejected",
+        })
+        .collect();
+    conn.execute(
+        "INSERT INTO contributions (pool_address, member, last_hash_at, digest, nonce, difficulty, score, status)
+        SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::BIGINT[], $4::BYTEA[], $5::BYTEA[], $6::INT[], $7::BIGINT[], $8::VARCHAR[])",
+        &[
+            &pool_address.to_string(),
+            &members,
+            &last_hash_ats,
+            &digests,
+            &nonces,
+            &difficulties,
+            &scores,
+            &statuses,
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+// reads the most recent contributions of a member
+pub async fn read_member_contributions(
+    conn: &Object,
+    pool_address: &Pubkey,
+    member: &Pubkey,
+    limit: i64,
+) -> Result<Vec<ContributionRecord>, Error> {
+    let rows = conn
+        .query(
+            "SELECT member, last_hash_at, digest, nonce, difficulty, score, status
+            FROM contributions
+            WHERE pool_address = $1 AND member = $2
+            ORDER BY last_hash_at DESC
+            LIMIT $3",
+            &[&pool_address.to_string(), &member.to_string(), &limit],
+        )
+        .await?;
+    rows.iter().map(contribution_record).collect()
+}
+
+pub async fn read_challenge_contributions(
+    conn: &Object,
+    pool_address: &Pubkey,
+    last_hash_at: i64,
+) -> Result<Vec<ContributionRecord>, Error> {
+    let rows = conn
+        .query(
+            "SELECT member, last_hash_at, digest, nonce, difficulty, score, status
+            FROM contributions
+            WHERE pool_address = $1 AND last_hash_at = $2",
+            &[&pool_address.to_string(), &last_hash_at],
+        )
+        .await?;
+    rows.iter().map(contribution_record).collect()
+}
+
+// deletes contributions to challenges before the cutoff,
+// returning the number of rows deleted
+pub async fn prune_contributions(
+    conn: &Object,
+    pool_address: &Pubkey,
+    cutoff: i64,
+) -> Result<u64, Error> {
+    let count = conn
+        .execute(
+            "DELETE FROM contributions WHERE pool_address = $1 AND last_hash_at < $2",
+            &[&pool_address.to_string(), &cutoff],
+        )
+        .await?;
+    Ok(count)
+}
+
+// persists a mining event with the reward and best score of each member,
+// replacing any record previously written for the same challenge
+pub async fn write_mining_event(
+    transaction: &Transaction<'_>,
+    pool_address: &Pubkey,
+    event: &PoolMiningEvent,
+) -> Result<(), Error> {
+    let pool_address = pool_address.to_string();
+    let mine_event = &event.mine_event;
+    let members: HashSet<&Pubkey> = event
+        .member_rewards
+        .keys()
+        .chain(event.member_scores.keys())
+        .collect();
+    let members: Vec<&Pubkey> = members.into_iter().collect();
+    let scores: Vec<i64> = members
+        .iter()
+        .map(|m| *event.member_scores.get(m).unwrap_or(&0) as i64)
+        .collect();
+    let rewards: Vec<i64> = members
+        .iter()
+        .map(|m| *event.member_rewards.get(m).unwrap_or(&0) as i64)
+        .collect();
+    let members: Vec<String> = members.iter().map(|m| m.to_string()).collect();
+    transaction
+        .execute(
+            "INSERT INTO mining_events (pool_address, last_hash_at, signature, block, timestamp, balance, difficulty, timing, net_reward, net_base_reward, net_miner_boost_reward, net_staker_boost_reward)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (pool_address, last_hash_at) DO UPDATE SET
+              signature = EXCLUDED.signature,
+              block = EXCLUDED.block,
+              timestamp = EXCLUDED.timestamp,
+              balance = EXCLUDED.balance,
+              difficulty = EXCLUDED.difficulty,
+              timing = EXCLUDED.timing,
+              net_reward = EXCLUDED.net_reward,
+              net_base_reward = EXCLUDED.net_base_reward,
+              net_miner_boost_reward = EXCLUDED.net_miner_boost_reward,
+              net_staker_boost_reward = EXCLUDED.net_staker_boost_reward",
+            &[
+                &pool_address,
+                &mine_event.last_hash_at,
+                &event.signature.to_string(),
+                &(event.block as i64),
+                &(event.timestamp as i64),
+                &(mine_event.balance as i64),
+                &(mine_event.difficulty as i64),
+                &mine_event.timing,
+                &(mine_event.net_reward as i64),
+                &(mine_event.net_base_reward as i64),
+                &(mine_event.net_miner_boost_reward as i64),
+                &(mine_event.net_staker_boost_reward as i64),
+            ],
+        )
+        .await?;
+    transaction
+        .execute(
+            "DELETE FROM mining_event_members WHERE pool_address = $1 AND last_hash_at = $2",
+            &[&pool_address, &mine_event.last_hash_at],
+        )
+        .await?;
+    transaction
+        .execute(
+            "INSERT INTO mining_event_members (pool_address, last_hash_at, member, score, reward)
+            SELECT $1, $2, * FROM UNNEST($3::VARCHAR[], $4::BIGINT[], $5::BIGINT[])",
+            &[
+                &pool_address,
+                &mine_event.last_hash_at,
+                &members,
+                &scores,
+                &rewards,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+// records a mining event that failed verification, for the operator to review
+pub async fn write_quarantined_event(
+    conn: &Object,
+    pool_address: &Pubkey,
+    signature: &Signature,
+    source: &str,
+    reason: &str,
+    mine_event: &ore_api::event::MineEvent,
+) -> Result<(), Error> {
+    conn.execute(
+        "INSERT INTO quarantined_events (pool_address, signature, last_hash_at, source, reason, mine_event)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT DO NOTHING",
+        &[
+            &pool_address.to_string(),
+            &signature.to_string(),
+            &mine_event.last_hash_at,
+            &source,
+            &reason,
+            &bytemuck::bytes_of(mine_event),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+// the signature of the most recent mining event processed before the timestamp
+pub async fn read_last_processed_signature(
+    conn: &Object,
+    pool_address: &Pubkey,
+    before: i64,
+) -> Result<Option<Signature>, Error> {
+    let row = conn
+        .query_opt(
+            "SELECT signature FROM processed_events
+            WHERE pool_address = $1 AND last_hash_at < $2
+            ORDER BY last_hash_at DESC
+            LIMIT 1",
+            &[&pool_address.to_string(), &before],
+        )
+        .await?;
+    match row {
+        Some(row) => {
+            let signature: String = row.try_get(0)?;
+            Ok(Some(Signature::from_str(signature.as_str())?))
+        }
+        None => Ok(None),
+    }
+}
+
+pub async fn processed_event_exists(
+    conn: &Object,
+    pool_address: &Pubkey,
+    last_hash_at: i64,
+) -> Result<bool, Error> {
+    let row = conn
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM processed_events WHERE pool_address = $1 AND last_hash_at = $2)",
+            &[&pool_address.to_string(), &last_hash_at],
+        )
+        .await?;
+    let exists: bool = row.try_get(0)?;
+    Ok(exists)
+}
+
+// reads the contributions to the last n challenges up to and including the given one,
+// for rebuilding the attribution window of a past mining event
+pub async fn read_contribution_window(
+    conn: &Object,
+    pool_address: &Pubkey,
+    last_hash_at: i64,
+    challenges: i64,
+) -> Result<Vec<ContributionRecord>, Error> {
+    let rows = conn
+        .query(
+            "SELECT member, last_hash_at, digest, nonce, difficulty, score, status
+            FROM contributions
+            WHERE pool_address = $1
+            AND status = 'accepted'
+            AND last_hash_at IN (
+              SELECT DISTINCT last_hash_at FROM contributions
+              WHERE pool_address = $1 AND last_hash_at <= $2
+              ORDER BY last_hash_at DESC
+              LIMIT $3
+            )",
+            &[&pool_address.to_string(), &last_hash_at, &challenges],
+        )
+        .await?;
+    rows.iter().map(contribution_record).collect()
+}
+
+// reads the mining events of the pool, most recent first
+pub async fn read_mining_events(
+    conn: &Object,
+    pool_address: &Pubkey,
+    query: &EventsQuery,
+    limit: i64,
+) -> Result<Vec<PoolMiningEventRecord>, Error> {
+    let rows = conn
+        .query(
+            "SELECT e.signature, e.block, e.timestamp, e.balance, e.difficulty, e.last_hash_at, e.timing,
+              e.net_reward, e.net_base_reward, e.net_miner_boost_reward, e.net_staker_boost_reward,
+              COALESCE(SUM(m.reward), 0)::BIGINT,
+              COUNT(m.member) FILTER (WHERE m.reward > 0)
+            FROM mining_events e
+            LEFT JOIN mining_event_members m
+              ON m.pool_address = e.pool_address AND m.last_hash_at = e.last_hash_at
+            WHERE e.pool_address = $1
+            AND ($2::BIGINT IS NULL OR e.last_hash_at < $2)
+            AND ($3::BIGINT IS NULL OR e.last_hash_at > $3)
+            AND ($4::BIGINT IS NULL OR e.timestamp >= $4)
+            AND ($5::BIGINT IS NULL OR e.timestamp <= $5)
+            GROUP BY e.pool_address, e.last_hash_at
+            ORDER BY e.last_hash_at DESC
+            LIMIT $6",
+            &[
+                &pool_address.to_string(),
+                &query.before,
+                &query.after,
+                &query.start_time,
+                &query.end_time,
+                &limit,
+            ],
+        )
+        .await?;
+    let mut events = Vec::with_capacity(rows.len());
+    for row in rows {
+        let signature: String = row.try_get(0)?;
+        let block: i64 = row.try_get(1)?;
+        let timestamp: i64 = row.try_get(2)?;
+        let balance: i64 = row.try_get(3)?;
+        let difficulty: i64 = row.try_get(4)?;
+        let net_reward: i64 = row.try_get(7)?;
+        let net_base_reward: i64 = row.try_get(8)?;
+        let net_miner_boost_reward: i64 = row.try_get(9)?;
+        let net_staker_boost_reward: i64 = row.try_get(10)?;
+        let total_member_rewards: i64 = row.try_get(11)?;
+        let total_members: i64 = row.try_get(12)?;
+        events.push(PoolMiningEventRecord {
+            signature: Signature::from_str(signature.as_str())?,
+            block: block as u64,
+            timestamp: timestamp as u64,
+            balance: balance as u64,
+            difficulty: difficulty as u64,
+            last_hash_at: row.try_get(5)?,
+            timing: row.try_get(6)?,
+            net_reward: net_reward as u64,
+            net_base_reward: net_base_reward as u64,
+            net_miner_boost_reward: net_miner_boost_reward as u64,
+            net_staker_boost_reward: net_staker_boost_reward as u64,
+            total_member_rewards: total_member_rewards as u64,
+            total_members: total_members as u64,
+        });
+    }
+    Ok(events)
+}
+
+// reads the mining events a member was rewarded or scored in, most recent first
+pub async fn read_member_mining_events(
+    conn: &Object,
+    pool_address: &Pubkey,
+    member: &Pubkey,
+    query: &EventsQuery,
+    limit: i64,
+) -> Result<Vec<PoolMemberMiningEvent>, Error> {
+    let rows = conn
+        .query(
+            "SELECT e.signature, e.block, e.timestamp, e.balance, e.difficulty, e.last_hash_at, e.timing,
+              e.net_reward, e.net_base_reward, e.net_miner_boost_reward, e.net_staker_boost_reward,
+              m.score, m.reward
+            FROM mining_event_members m
+            JOIN mining_events e
+              ON e.pool_address = m.pool_address AND e.last_hash_at = m.last_hash_at
+            WHERE m.pool_address = $1
+            AND m.member = $2
+            AND ($3::BIGINT IS NULL OR e.last_hash_at < $3)
+            AND ($4::BIGINT IS NULL OR e.last_hash_at > $4)
+            AND ($5::BIGINT IS NULL OR e.timestamp >= $5)
+            AND ($6::BIGINT IS NULL OR e.timestamp <= $6)
+            ORDER BY e.last_hash_at DESC
+            LIMIT $7",
+            &[
+                &pool_address.to_string(),
+                &member.to_string(),
+                &query.before,
+                &query.after,
+                &query.start_time,
+                &query.end_time,
+                &limit,
+            ],
+        )
+        .await?;
+    let mut events = Vec::with_capacity(rows.len());
+    for row in rows {
+        let signature: String = row.try_get(0)?;
+        let block: i64 = row.try_get(1)?;
+        let timestamp: i64 = row.try_get(2)?;
+        let balance: i64 = row.try_get(3)?;
+        let difficulty: i64 = row.try_get(4)?;
+        let net_reward: i64 = row.try_get(7)?;
+        let net_base_reward: i64 = row.try_get(8)?;
+        let net_miner_boost_reward: i64 = row.try_get(9)?;
+        let net_staker_boost_reward: i64 = row.try_get(10)?;
+        let score: i64 = row.try_get(11)?;
+        let reward: i64 = row.try_get(12)?;
+        events.push(PoolMemberMiningEvent {
+            signature: Signature::from_str(signature.as_str())?,
+            block: block as u64,
+            timestamp: timestamp as u64,
+            balance: balance as u64,
+            difficulty: difficulty as u64,
+            last_hash_at: row.try_get(5)?,
+            timing: row.try_get(6)?,
+            net_reward: net_reward as u64,
+            net_base_reward: net_base_reward as u64,
+            net_miner_boost_reward: net_miner_boost_reward as u64,
+            net_staker_boost_reward: net_staker_boost_reward as u64,
+            member_difficulty: if score > 0 {
+                (score as u64).ilog2() as u64
+            } else {
+                0
+            },
+            member_reward: reward as u64,
+        });
+    }
+    Ok(events)
+}
+
+// upserts the latest snapshot of the aggregator for the pool
+pub async fn write_snapshot(
+    conn: &Object,
+    pool_address: &Pubkey,
+    data: &[u8],
+) -> Result<(), Error> {
+    conn.execute(
+        "INSERT INTO aggregator_snapshots (pool_address, data, updated_at)
+        VALUES ($1, $2, NOW())
+        ON CONFLICT (pool_address) DO UPDATE SET data = EXCLUDED.data, updated_at = NOW()",
+        &[&pool_address.to_string(), &data],
+    )
+    .await?;
+    Ok(())
+}
+
+// records the state of the reward policy,
+// in the transaction of the mining event that changed it
+pub async fn write_reward_policy_state(
+    transaction: &Transaction<'_>,
+    pool_address: &Pubkey,
+    policy: &str,
+    data: &[u8],
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "INSERT INTO reward_policy_states (pool_address, policy, data, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (pool_address, policy) DO UPDATE SET data = EXCLUDED.data, updated_at = NOW()",
+            &[&pool_address.to_string(), &policy, &data],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn read_reward_policy_state(
+    conn: &Object,
+    pool_address: &Pubkey,
+    policy: &str,
+) -> Result<Option<Vec<u8>>, Error> {
+    let row = conn
+        .query_opt(
+            "SELECT data FROM reward_policy_states WHERE pool_address = $1 AND policy = $2",
+            &[&pool_address.to_string(), &policy],
+        )
+        .await?;
+    match row {
+        Some(row) => Ok(Some(row.try_get(0)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn read_snapshot(conn: &Object, pool_address: &Pubkey) -> Result<Option<Vec<u8>>, Error> {
+    let row = conn
+        .query_opt(
+            "SELECT data FROM aggregator_snapshots WHERE pool_address = $1",
+            &[&pool_address.to_string()],
+        )
+        .await?;
+    match row {
+        Some(row) => Ok(Some(row.try_get(0)?)),
+        None => Ok(None),
+    }
+}
+
+// upserts a ban, keeping the row once expired to escalate repeat offences
+pub async fn write_ban(conn: &Object, pool_address: &Pubkey, ban: &Ban) -> Result<(), Error> {
+    conn.execute(
+        "INSERT INTO bans (pool_address, subject, reason, offences, banned_at, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (pool_address, subject) DO UPDATE SET
+            reason = EXCLUDED.reason,
+            offences = EXCLUDED.offences,
+            banned_at = EXCLUDED.banned_at,
+            expires_at = EXCLUDED.expires_at",
+        &[
+            &pool_address.to_string(),
+            &ban.subject,
+            &ban.reason,
+            &(ban.offences as i32),
+            &ban.banned_at,
+            &ban.expires_at,
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+// reads every ban, expired included, most recent first
+pub async fn read_bans(conn: &Object, pool_address: &Pubkey) -> Result<Vec<Ban>, Error> {
+    let rows = conn
+        .query(
+            "SELECT subject, reason, offences, banned_at, expires_at
+            FROM bans
+            WHERE pool_address = $1
+            ORDER BY banned_at DESC",
+            &[&pool_address.to_string()],
+        )
+        .await?;
+    let mut bans = Vec::with_capacity(rows.len());
+    for row in rows {
+        let offences: i32 = row.try_get(2)?;
+        bans.push(Ban {
+            subject: row.try_get(0)?,
+            reason: row.try_get(1)?,
+            offences: offences as u32,
+            banned_at: row.try_get(3)?,
+            expires_at: row.try_get(4)?,
+        });
+    }
+    Ok(bans)
+}
+
+// returns whether a ban was deleted
+pub async fn delete_ban(
+    conn: &Object,
+    pool_address: &Pubkey,
+    subject: &str,
+) -> Result<bool, Error> {
+    let count = conn
+        .execute(
+            "DELETE FROM bans WHERE pool_address = $1 AND subject = $2",
+            &[&pool_address.to_string(), &subject],
+        )
+        .await?;
+    Ok(count > 0)
+}
+
+fn contribution_record(row: &Row) -> Result<ContributionRecord, Error> {
+    let member: String = row.try_get(0)?;
+    let digest: Vec<u8> = row.try_get(2)?;
+    let nonce: Vec<u8> = row.try_get(3)?;
+    let difficulty: i32 = row.try_get(4)?;
+    let score: i64 = row.try_get(5)?;
+    let status: String = row.try_get(6)?;
+    Ok(ContributionRecord {
+        member: Pubkey::from_str(member.as_str())?,
+        last_hash_at: row.try_get(1)?,
+        digest: digest.as_slice().try_into()?,
+        nonce: nonce.as_slice().try_into()?,
+        difficulty: difficulty as u32,
+        score: score as u64,
+        status: match status.as_str() {
+            "accepted" => ContributionStatus::Accepted,
+            _ => ContributionStatus::Rejected,
+        },
+    })
+}
+
 pub async fn write_synced_members(conn: &Object, address_buffer: &[String]) -> Result<(), Error> {
     let query = "UPDATE members SET is_synced = true WHERE address = ANY($1)";
     conn.execute(query, &[&address_buffer]).await?;
     Ok(())
 }
 
+// writes the record of an on-chain member account,
+// starting from its lifetime balance as the account may have been transferred
 pub async fn write_new_member(
     conn: &Object,
-    member: &ore_pool_api::state::Member,
+    member: &Member,
     approved: bool,
 ) -> Result<ore_pool_types::Member, Error> {
     let member = ore_pool_types::Member {
@@ -135,7 +897,7 @@ pub async fn write_new_member(
         id: (member.id as i64),
         authority: member.authority.to_string(),
         pool_address: member.pool.to_string(),
-        total_balance: 0,
+        total_balance: member.total_balance as i64,
         is_approved: approved,
         is_kyc: false,
         is_synced: true,
@@ -159,6 +921,86 @@ pub async fn write_new_member(
     Ok(member)
 }
 
+// moves a member record to the account of its new authority,
+// keeping the id and balances
+pub async fn transfer_member(
+    conn: &Object,
+    address: &String,
+    new_address: &String,
+    new_authority: &String,
+) -> Result<(), Error> {
+    conn.execute(
+        "UPDATE members SET address = $1, authority = $2 WHERE address = $3",
+        &[new_address, new_authority, address],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_member(conn: &Object, address: &String) -> Result<(), Error> {
+    conn.execute("DELETE FROM members WHERE address = $1", &[address])
+        .await?;
+    Ok(())
+}
+
+// reads every member of the pool, for the member directory
+pub async fn read_members(
+    conn: &Object,
+    pool_address: &Pubkey,
+) -> Result<Vec<ore_pool_types::Member>, Error> {
+    let rows = conn
+        .query(
+            "SELECT address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced
+            FROM members
+            WHERE pool_address = $1",
+            &[&pool_address.to_string()],
+        )
+        .await?;
+    let mut members = Vec::with_capacity(rows.len());
+    for row in rows {
+        members.push(ore_pool_types::Member {
+            address: row.try_get(0)?,
+            id: row.try_get(1)?,
+            authority: row.try_get(2)?,
+            pool_address: row.try_get(3)?,
+            total_balance: row.try_get(4)?,
+            is_approved: row.try_get(5)?,
+            is_kyc: row.try_get(6)?,
+            is_synced: row.try_get(7)?,
+        });
+    }
+    Ok(members)
+}
+
+// reads the member of the pool holding the id, if any
+pub async fn read_member_by_id(
+    conn: &Object,
+    pool_address: &Pubkey,
+    id: u64,
+) -> Result<Option<ore_pool_types::Member>, Error> {
+    let row = conn
+        .query_opt(
+            "SELECT address, id, authority, pool_address, total_balance, is_approved, is_kyc, is_synced
+            FROM members
+            WHERE pool_address = $1 AND id = $2",
+            &[&pool_address.to_string(), &(id as i64)],
+        )
+        .await?;
+    match row {
+        Some(row) => Ok(Some(ore_pool_types::Member {
+            address: row.try_get(0)?,
+            id: row.try_get(1)?,
+            authority: row.try_get(2)?,
+            pool_address: row.try_get(3)?,
+            total_balance: row.try_get(4)?,
+            is_approved: row.try_get(5)?,
+            is_kyc: row.try_get(6)?,
+            is_synced: row.try_get(7)?,
+        })),
+        None => Ok(None),
+    }
+}
+
 pub async fn read_member(conn: &Object, address: &String) -> Result<ore_pool_types::Member, Error> {
     let row = conn
         .query_one(
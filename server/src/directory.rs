@@ -0,0 +1,198 @@
+use std::{collections::HashSet, str::FromStr};
+
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use tokio_postgres::{AsyncMessage, NoTls};
+
+use crate::{database, error::Error, operator::Operator};
+
+/// The channel the members table notifies changes on.
+const MEMBERS_CHANNEL: &str = "members";
+
+/// Maps member authorities to their ids and approval flags,
+/// so that contributions are validated without a database round trip.
+pub struct MemberDirectory {
+    /// The pool the members belong to.
+    pool_address: String,
+
+    /// The directory entry of each member authority.
+    members: DashMap<Pubkey, DirectoryEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirectoryEntry {
+    /// The id as assigned by the on-chain program.
+    pub id: u64,
+
+    /// Whether or not this member is approved by the operator.
+    pub is_approved: bool,
+
+    /// Whether or not this member is KYC'd by the operator.
+    pub is_kyc: bool,
+}
+
+/// The payload of a members table notification.
+#[derive(Deserialize, Debug)]
+struct MemberNotification {
+    op: String,
+    authority: String,
+    old_authority: Option<String>,
+    pool_address: String,
+    id: i64,
+    is_approved: bool,
+    is_kyc: bool,
+}
+
+impl MemberDirectory {
+    /// loads every member of the pool
+    pub async fn new(operator: &Operator) -> Result<Self, Error> {
+        let directory = Self {
+            pool_address: operator.pool_address().to_string(),
+            members: DashMap::new(),
+        };
+        directory.reload(operator).await?;
+        Ok(directory)
+    }
+
+    pub fn get(&self, authority: &Pubkey) -> Option<DirectoryEntry> {
+        self.members.get(authority).map(|entry| *entry)
+    }
+
+    pub fn insert(&self, member: &ore_pool_types::Member) {
+        self.upsert(
+            member.authority.as_str(),
+            member.pool_address.as_str(),
+            DirectoryEntry {
+                id: member.id as u64,
+                is_approved: member.is_approved,
+                is_kyc: member.is_kyc,
+            },
+        );
+    }
+
+    fn upsert(&self, authority: &str, pool_address: &str, entry: DirectoryEntry) {
+        if pool_address.ne(self.pool_address.as_str()) {
+            return;
+        }
+        let authority = match Pubkey::from_str(authority) {
+            Ok(authority) => authority,
+            Err(err) => {
+                log::error!("invalid member authority: {} {:?}", authority, err);
+                return;
+            }
+        };
+        // only log changes, as reloads upsert every member
+        if self
+            .members
+            .insert(authority, entry)
+            .is_none_or(|prev| prev != entry)
+        {
+            log::info!(
+                "member directory: {} id: {} approved: {} kyc: {}",
+                authority,
+                entry.id,
+                entry.is_approved,
+                entry.is_kyc
+            );
+        }
+    }
+
+    pub fn remove(&self, authority: &str) {
+        if let Ok(authority) = Pubkey::from_str(authority) {
+            if self.members.remove(&authority).is_some() {
+                log::info!("member directory: {} removed", authority);
+            }
+        }
+    }
+
+    /// reads every member of the pool from the database,
+    /// dropping the members that are no longer there
+    async fn reload(&self, operator: &Operator) -> Result<(), Error> {
+        let db_client = operator.db_client.get().await?;
+        let members = database::read_members(&db_client, &operator.pool_address()).await?;
+        let mut authorities = HashSet::with_capacity(members.len());
+        for member in members.iter() {
+            self.insert(member);
+            authorities.insert(member.authority.as_str());
+        }
+        self.members
+            .retain(|authority, _| authorities.contains(authority.to_string().as_str()));
+        log::info!("member directory loaded: {}", self.members.len());
+        Ok(())
+    }
+
+    fn apply(&self, payload: &str) -> Result<(), Error> {
+        let notification: MemberNotification = serde_json::from_str(payload)?;
+        if notification.pool_address.ne(&self.pool_address) {
+            return Ok(());
+        }
+        // a transfer moves the member to its new authority
+        if let Some(old_authority) = notification.old_authority.as_ref() {
+            if old_authority.ne(&notification.authority) {
+                self.remove(old_authority.as_str());
+            }
+        }
+        match notification.op.as_str() {
+            "DELETE" => self.remove(notification.authority.as_str()),
+            _ => self.upsert(
+                notification.authority.as_str(),
+                notification.pool_address.as_str(),
+                DirectoryEntry {
+                    id: notification.id as u64,
+                    is_approved: notification.is_approved,
+                    is_kyc: notification.is_kyc,
+                },
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Listens for changes to the members table on a dedicated connection,
+/// and applies them to the directory.
+/// Returns once the connection closes, for the caller to reconnect.
+pub async fn listen(directory: &MemberDirectory, operator: &Operator) -> Result<(), Error> {
+    let db_url = std::env::var("DB_URL")?;
+    let (client, mut connection) = tokio_postgres::connect(db_url.as_str(), NoTls).await?;
+
+    // drive the connection, forwarding its notifications
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let driver = tokio::task::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!("{:?}", err);
+                    break;
+                }
+            }
+        }
+    });
+    client
+        .batch_execute(format!("LISTEN {}", MEMBERS_CHANNEL).as_str())
+        .await?;
+
+    // reload once listening, so no change made while disconnected is missed
+    directory.reload(operator).await?;
+    log::info!("listening for member changes");
+    while let Some(notification) = rx.recv().await {
+        if notification.channel().ne(MEMBERS_CHANNEL) {
+            continue;
+        }
+        if let Err(err) = directory.apply(notification.payload()) {
+            log::error!("{:?}", err);
+        }
+    }
+    driver.abort();
+    Err(Error::Internal(
+        "member notification connection closed".to_string(),
+    ))
+}
@@ -31,12 +31,18 @@ pub enum Error {
     StdParseInt(#[from] std::num::ParseIntError),
     #[error("solana client")]
     SolanaClient(#[from] solana_client::client_error::ClientError),
+    #[error("solana pubsub client")]
+    SolanaPubsubClient(#[from] solana_client::nonblocking::pubsub_client::PubsubClientError),
     #[error("solana program")]
     SolanaProgram(#[from] solana_sdk::program_error::ProgramError),
     #[error("solana pubkey")]
     SolanaPubkey(#[from] solana_sdk::pubkey::ParsePubkeyError),
+    #[error("solana signature")]
+    SolanaSignature(#[from] solana_sdk::signature::ParseSignatureError),
     #[error("member doesn't exist yet")]
     MemberDoesNotExist,
+    #[error("member has earnings that are not attributed on-chain yet")]
+    MemberNotSynced,
     #[error("staker doesn't exist yet")]
     StakerDoesNotExist,
     #[error("share account received")]
@@ -54,6 +60,7 @@ impl From<Error> for HttpResponse {
                 HttpResponse::NotFound().finish()
             }
             Error::ShareAccountReceived => HttpResponse::Ok().finish(),
+            Error::MemberNotSynced => HttpResponse::Conflict().body(value.to_string()),
             _ => HttpResponse::InternalServerError().finish(),
         }
     }
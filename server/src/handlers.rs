@@ -1,22 +1,84 @@
 use std::str::FromStr;
 
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use arc_swap::ArcSwap;
+use futures::future::Either;
+use ore_pool_api::merkle::{contribution_leaf, MerkleTree};
 use ore_pool_types::{
-    BalanceUpdate, ContributePayloadV2, GetChallengePayload, GetEventPayload, GetMemberPayload,
-    MemberChallenge, PoolAddress, PoolMemberMiningEvent, RegisterPayload, UpdateBalancePayload,
+    AttestationProof, BalanceUpdate, Challenge, ContributeCode, ContributePayloadV2,
+    ContributeResponse, ContributionRecord, ContributionStatus, ContributionsQuery, EventsQuery,
+    GetAttestationProofPayload, GetBanPayload, GetChallengeContributionsPayload,
+    GetChallengePayload, GetEventPayload, GetMemberPayload, LeavePayload, MemberChallenge,
+    PoolAddress, PoolMemberMiningEvent, RegisterPayload, TransferMemberPayload,
+    UpdateBalancePayload,
 };
 use solana_sdk::{pubkey::Pubkey, signer::Signer};
 
-use crate::{aggregator::Aggregator, database, error::Error, operator::Operator, tx, Contribution};
+use crate::{
+    aggregator::{Aggregator, ChallengeUpdate, MAX_SCORE},
+    balances::BalanceTree,
+    database,
+    directory::MemberDirectory,
+    error::Error,
+    limiter::{self, Limiter},
+    operator::Operator,
+    round::{Insert, Round},
+    tx, Contribution,
+};
 
 const NUM_CLIENT_DEVICES: u8 = 5;
+const MAX_CONTRIBUTION_RECORDS: i64 = 1_000;
+const MAX_EVENT_RECORDS: i64 = 1_000;
 
 pub async fn register(
     operator: web::Data<Operator>,
+    directory: web::Data<MemberDirectory>,
     payload: web::Json<RegisterPayload>,
 ) -> impl Responder {
     let operator = operator.as_ref();
-    let res = register_new_member(operator, payload.into_inner()).await;
+    let res = register_new_member(operator, directory.as_ref(), payload.into_inner()).await;
+    match res {
+        Ok(db_member) => {
+            // available for contributions without waiting on the notification
+            directory.insert(&db_member);
+            HttpResponse::Ok().json(&db_member)
+        }
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
+pub async fn leave(
+    operator: web::Data<Operator>,
+    directory: web::Data<MemberDirectory>,
+    payload: web::Json<LeavePayload>,
+) -> impl Responder {
+    let operator = operator.as_ref();
+    let authority = payload.authority;
+    match leave_pool(operator, payload.into_inner()).await {
+        Ok(()) => {
+            // no longer accepted for contributions without waiting on the notification
+            directory.remove(authority.to_string().as_str());
+            HttpResponse::Ok().finish()
+        }
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
+pub async fn transfer(
+    operator: web::Data<Operator>,
+    directory: web::Data<MemberDirectory>,
+    payload: web::Json<TransferMemberPayload>,
+) -> impl Responder {
+    let operator = operator.as_ref();
+    let res = transfer_member(operator, directory.as_ref(), payload.into_inner()).await;
     match res {
         Ok(db_member) => HttpResponse::Ok().json(&db_member),
         Err(err) => {
@@ -29,7 +91,7 @@ pub async fn register(
 
 pub async fn address(operator: web::Data<Operator>) -> impl Responder {
     let operator = operator.as_ref();
-    let (pool_pda, bump) = ore_pool_api::state::pool_pda(operator.keypair.pubkey());
+    let (pool_pda, bump) = ore_pool_api::state::pool_pda(operator.pool_authority);
     HttpResponse::Ok().json(&PoolAddress {
         address: pool_pda,
         bump,
@@ -65,8 +127,169 @@ pub async fn member(
     }
 }
 
+pub async fn member_proof(
+    balance_tree: web::Data<tokio::sync::RwLock<BalanceTree>>,
+    path: web::Path<GetMemberPayload>,
+) -> impl Responder {
+    // Parse member authority
+    let authority = match Pubkey::from_str(path.authority.as_str()) {
+        Ok(authority) => authority,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(err.to_string());
+        }
+    };
+
+    // Read proof against the posted balance root
+    let balance_tree = balance_tree.read().await;
+    match balance_tree.proof(&authority) {
+        Some(proof) => HttpResponse::Ok().json(&proof),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+pub async fn attestation_proof(
+    operator: web::Data<Operator>,
+    path: web::Path<GetAttestationProofPayload>,
+) -> impl Responder {
+    let path = path.into_inner();
+    match build_attestation_proof(operator.as_ref(), path).await {
+        Ok(proof) => HttpResponse::Ok().json(&proof),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
+pub async fn member_contributions(
+    operator: web::Data<Operator>,
+    path: web::Path<GetMemberPayload>,
+    query: web::Query<ContributionsQuery>,
+) -> impl Responder {
+    // Parse member authority
+    let authority = match Pubkey::from_str(path.authority.as_str()) {
+        Ok(authority) => authority,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(err.to_string());
+        }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(MAX_CONTRIBUTION_RECORDS)
+        .clamp(1, MAX_CONTRIBUTION_RECORDS);
+    let res = match operator.db_client.get().await {
+        Ok(db_client) => {
+            database::read_member_contributions(
+                &db_client,
+                &operator.pool_address(),
+                &authority,
+                limit,
+            )
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match res {
+        Ok(records) => HttpResponse::Ok().json(&records),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
+pub async fn challenge_contributions(
+    operator: web::Data<Operator>,
+    path: web::Path<GetChallengeContributionsPayload>,
+) -> impl Responder {
+    let res = match operator.db_client.get().await {
+        Ok(db_client) => {
+            database::read_challenge_contributions(
+                &db_client,
+                &operator.pool_address(),
+                path.last_hash_at,
+            )
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match res {
+        Ok(records) => HttpResponse::Ok().json(&records),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
+pub async fn events(
+    operator: web::Data<Operator>,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(MAX_EVENT_RECORDS)
+        .clamp(1, MAX_EVENT_RECORDS);
+    let res = match operator.db_client.get().await {
+        Ok(db_client) => {
+            database::read_mining_events(&db_client, &operator.pool_address(), &query, limit).await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match res {
+        Ok(events) => HttpResponse::Ok().json(&events),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
+pub async fn member_events(
+    operator: web::Data<Operator>,
+    path: web::Path<GetMemberPayload>,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    // Parse member authority
+    let authority = match Pubkey::from_str(path.authority.as_str()) {
+        Ok(authority) => authority,
+        Err(err) => {
+            return HttpResponse::BadRequest().body(err.to_string());
+        }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(MAX_EVENT_RECORDS)
+        .clamp(1, MAX_EVENT_RECORDS);
+    let res = match operator.db_client.get().await {
+        Ok(db_client) => {
+            database::read_member_mining_events(
+                &db_client,
+                &operator.pool_address(),
+                &authority,
+                &query,
+                limit,
+            )
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match res {
+        Ok(events) => HttpResponse::Ok().json(&events),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
+
 pub async fn challenge(
-    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
+    challenge_rx: web::Data<tokio::sync::watch::Receiver<ChallengeUpdate>>,
     clock_tx: web::Data<tokio::sync::broadcast::Sender<i64>>,
     _path: web::Path<GetChallengePayload>,
 ) -> impl Responder {
@@ -79,42 +302,191 @@ pub async fn challenge(
             return HttpResponse::InternalServerError().body(err.to_string());
         }
     };
-    // Acquire write on aggregator for challenge
-    let (challenge, last_num_members) = {
-        let aggregator = aggregator.read().await;
-        (aggregator.current_challenge, aggregator.num_members)
+    let member_challenge = member_challenge(&challenge_rx.borrow(), unix_timestamp);
+    HttpResponse::Ok().json(member_challenge)
+}
+
+/// Streams server-sent events to the miner:
+/// a "challenge" event with the member challenge as soon as a new challenge is installed,
+/// and a "clock" event with the unix timestamp on each tick of the clock.
+pub async fn challenge_stream(
+    challenge_rx: web::Data<tokio::sync::watch::Receiver<ChallengeUpdate>>,
+    clock_tx: web::Data<tokio::sync::broadcast::Sender<i64>>,
+) -> impl Responder {
+    // Read from clock
+    let mut clock_rx = clock_tx.subscribe();
+    let unix_timestamp = match clock_rx.recv().await {
+        Ok(ts) => ts,
+        Err(err) => {
+            log::error!("{:?}", err);
+            return HttpResponse::InternalServerError().body(err.to_string());
+        }
     };
 
+    // Subscribe to new challenges,
+    // starting with the current challenge
+    let mut challenge_rx = challenge_rx.as_ref().clone();
+    challenge_rx.mark_changed();
+    let state = (challenge_rx, clock_rx, unix_timestamp);
+    let stream = futures::stream::unfold(state, |mut state| async move {
+        let (challenge_rx, clock_rx, unix_timestamp) = &mut state;
+        loop {
+            let next = match futures::future::select(
+                Box::pin(challenge_rx.changed()),
+                Box::pin(clock_rx.recv()),
+            )
+            .await
+            {
+                Either::Left((res, _)) => Either::Left(res),
+                Either::Right((res, _)) => Either::Right(res),
+            };
+            let event = match next {
+                Either::Left(Ok(())) => {
+                    let member_challenge =
+                        member_challenge(&challenge_rx.borrow_and_update(), *unix_timestamp);
+                    match serde_json::to_string(&member_challenge) {
+                        Ok(json) => format!("event: challenge\ndata: {}\n\n", json),
+                        Err(err) => {
+                            log::error!("{:?}", err);
+                            continue;
+                        }
+                    }
+                }
+                Either::Right(Ok(ts)) => {
+                    *unix_timestamp = ts;
+                    format!("event: clock\ndata: {}\n\n", ts)
+                }
+                // skip ticks missed by a slow client
+                Either::Right(Err(tokio::sync::broadcast::error::RecvError::Lagged(_))) => {
+                    continue;
+                }
+                // server is shutting down
+                Either::Left(Err(_)) | Either::Right(Err(_)) => return None,
+            };
+            return Some((Ok::<_, actix_web::Error>(web::Bytes::from(event)), state));
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+fn member_challenge(update: &ChallengeUpdate, unix_timestamp: i64) -> MemberChallenge {
     // Build member challenge
     #[allow(deprecated)]
-    let member_challenge = MemberChallenge {
-        challenge,
-        num_total_members: last_num_members,
+    MemberChallenge {
+        challenge: update.challenge,
+        num_total_members: update.num_members,
         device_id: 0,
         num_devices: NUM_CLIENT_DEVICES,
-        unix_timestamp: unix_timestamp,
-    };
-    HttpResponse::Ok().json(&member_challenge)
+        unix_timestamp,
+    }
 }
 
 /// Accepts solutions from pool members. If their solutions are valid, it
 /// aggregates the contributions into a list for publishing and submission.
 pub async fn contribute(
+    req: HttpRequest,
     operator: web::Data<Operator>,
-    aggregator: web::Data<tokio::sync::RwLock<Aggregator>>,
-    tx: web::Data<tokio::sync::mpsc::UnboundedSender<Contribution>>,
+    directory: web::Data<MemberDirectory>,
+    limiter: web::Data<Limiter>,
+    round: web::Data<ArcSwap<Round>>,
+    records_tx: web::Data<tokio::sync::mpsc::UnboundedSender<ContributionRecord>>,
     payload: web::Json<ContributePayloadV2>,
 ) -> impl Responder {
-    // acquire read on aggregator for challenge
-    let aggregator = aggregator.read().await;
-    let challenge = aggregator.current_challenge;
-    let num_members = aggregator.num_members;
-    drop(aggregator);
+    // enforce bans and the ip rate limit before any expensive work,
+    // the authority is unsigned so it is only charged once the contribution is valid
+    let ip = limiter.client_ip(&req);
+    let code = if limiter.banned(ip.as_str()).is_some()
+        || limiter
+            .banned(payload.authority.to_string().as_str())
+            .is_some()
+    {
+        Some(ContributeCode::Banned)
+    } else if !limiter.allow(ip.as_str()) {
+        Some(ContributeCode::RateLimited)
+    } else {
+        None
+    };
+    if let Some(code) = code {
+        return contribute_response(ContributeResponse {
+            code,
+            difficulty: 0,
+            score: 0,
+        });
+    }
+
+    let res = process_contribution(
+        directory.as_ref(),
+        limiter.as_ref(),
+        round.as_ref(),
+        records_tx.as_ref(),
+        payload.into_inner(),
+    )
+    .await;
+    match res {
+        Ok(response) => {
+            // count invalid contributions towards a ban of the sender,
+            // the authority is unsigned so it could be framed
+            if limiter::is_offence(response.code) {
+                if let Err(err) = limiter
+                    .offend(operator.as_ref(), ip.as_str(), response.code)
+                    .await
+                {
+                    log::error!("{:?}", err);
+                }
+            }
+            contribute_response(response)
+        }
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
 
+fn contribute_response(response: ContributeResponse) -> HttpResponse {
+    let mut http_response = match response.code {
+        ContributeCode::Accepted => HttpResponse::Ok(),
+        ContributeCode::NonceOutOfRange | ContributeCode::UnknownMember => {
+            HttpResponse::Unauthorized()
+        }
+        ContributeCode::RateLimited => HttpResponse::TooManyRequests(),
+        ContributeCode::Banned => HttpResponse::Forbidden(),
+        _ => HttpResponse::BadRequest(),
+    };
+    http_response.json(response)
+}
+
+async fn process_contribution(
+    directory: &MemberDirectory,
+    limiter: &Limiter,
+    round: &ArcSwap<Round>,
+    records_tx: &tokio::sync::mpsc::UnboundedSender<ContributionRecord>,
+    payload: ContributePayloadV2,
+) -> Result<ContributeResponse, Error> {
     // decode solution difficulty
-    let solution = &payload.solution;
+    let solution = payload.solution;
     let difficulty = solution.to_hash().difficulty();
-    let score = 2u64.pow(difficulty);
+    let score = 2u64.pow(difficulty).min(MAX_SCORE);
+    let reject = |code: ContributeCode| ContributeResponse {
+        code,
+        difficulty,
+        score: 0,
+    };
+
+    let contribution = Contribution {
+        member: payload.authority,
+        score,
+        solution,
+    };
+
+    // load the open round for challenge, without locking the aggregator
+    let round = round.load_full();
+    let challenge = round.challenge;
+    let previous_challenge = round.previous_challenge;
 
     // error if solution below min difficulty
     if difficulty < (challenge.min_difficulty as u32) {
@@ -124,28 +496,189 @@ pub async fn contribute(
             difficulty,
             challenge.min_difficulty
         );
-        return HttpResponse::BadRequest().finish();
+        return Ok(reject(ContributeCode::BelowMinDifficulty));
+    }
+
+    // error if the member already contributed an equal or better score
+    if round
+        .score(&payload.authority)
+        .is_some_and(|prev_score| prev_score >= score)
+    {
+        return Ok(reject(ContributeCode::Duplicate));
     }
 
     // validate nonce
     let member_authority = &payload.authority;
-    let nonce = solution.n;
-    let nonce = u64::from_le_bytes(nonce);
-    if let Err(err) = validate_nonce(operator.as_ref(), member_authority, nonce, num_members).await
-    {
+    let nonce = u64::from_le_bytes(solution.n);
+    match validate_nonce(directory, member_authority, nonce, round.num_members) {
+        Ok(true) => {}
+        Ok(false) => {
+            log::error!("invalid nonce from client: {:?}", member_authority);
+            return Ok(reject(ContributeCode::NonceOutOfRange));
+        }
+        Err(err) => {
+            log::error!("{:?}", err);
+            return Ok(reject(ContributeCode::UnknownMember));
+        }
+    }
+
+    // validate digest against the current challenge, off the async workers
+    let valid_digest = web::block(move || {
+        if drillx::is_valid_digest(&challenge.challenge, &solution.n, &solution.d) {
+            return Ok(());
+        }
+        match previous_challenge {
+            Some(previous_challenge)
+                if drillx::is_valid_digest(
+                    &previous_challenge.challenge,
+                    &solution.n,
+                    &solution.d,
+                ) =>
+            {
+                Err(ContributeCode::StaleChallenge)
+            }
+            _ => Err(ContributeCode::InvalidDigest),
+        }
+    })
+    .await;
+    match valid_digest {
+        Ok(Ok(())) => {}
+        Ok(Err(code)) => {
+            log::error!("{:?}: {:?}", code, member_authority);
+            record_contribution(
+                records_tx,
+                &contribution,
+                &challenge,
+                difficulty,
+                ContributionStatus::Rejected,
+            );
+            return Ok(reject(code));
+        }
+        Err(err) => return Err(Error::Internal(err.to_string())),
+    }
+
+    // rate limit the member, now that the contribution is known to be theirs
+    if !limiter.allow(member_authority.to_string().as_str()) {
+        return Ok(reject(ContributeCode::RateLimited));
+    }
+
+    // insert into the round
+    let code = match round.insert(contribution, difficulty) {
+        Insert::New | Insert::Updated => ContributeCode::Accepted,
+        Insert::Duplicate => ContributeCode::Duplicate,
+        // the round was sealed for submission since it was loaded
+        Insert::Sealed => ContributeCode::StaleChallenge,
+    };
+    let accepted = code == ContributeCode::Accepted;
+    record_contribution(
+        records_tx,
+        &contribution,
+        &challenge,
+        difficulty,
+        if accepted {
+            ContributionStatus::Accepted
+        } else {
+            ContributionStatus::Rejected
+        },
+    );
+    if !accepted {
+        return Ok(reject(code));
+    }
+    Ok(ContributeResponse {
+        code,
+        difficulty,
+        score,
+    })
+}
+
+/// sends a record of the contribution to be persisted
+fn record_contribution(
+    records_tx: &tokio::sync::mpsc::UnboundedSender<ContributionRecord>,
+    contribution: &Contribution,
+    challenge: &Challenge,
+    difficulty: u32,
+    status: ContributionStatus,
+) {
+    let record = ContributionRecord {
+        member: contribution.member,
+        last_hash_at: challenge.lash_hash_at,
+        digest: contribution.solution.d,
+        nonce: contribution.solution.n,
+        difficulty,
+        score: contribution.score,
+        status,
+    };
+    if let Err(err) = records_tx.send(record) {
+        log::error!("{:?}", err);
+    }
+}
+
+/// The token admin requests must carry in the authorization header.
+pub struct AdminToken(pub String);
+
+pub async fn bans(
+    req: HttpRequest,
+    admin_token: web::Data<AdminToken>,
+    operator: web::Data<Operator>,
+) -> impl Responder {
+    if let Err(err) = authorize_admin(&req, &admin_token) {
         log::error!("{:?}", err);
         return HttpResponse::Unauthorized().finish();
     }
+    let res = match operator.db_client.get().await {
+        Ok(db_client) => database::read_bans(&db_client, &operator.pool_address()).await,
+        Err(err) => Err(err.into()),
+    };
+    match res {
+        Ok(bans) => HttpResponse::Ok().json(&bans),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
+    }
+}
 
-    // update the aggegator
-    if let Err(err) = tx.send(Contribution {
-        member: payload.authority,
-        score,
-        solution: payload.solution,
-    }) {
+pub async fn unban(
+    req: HttpRequest,
+    admin_token: web::Data<AdminToken>,
+    operator: web::Data<Operator>,
+    limiter: web::Data<Limiter>,
+    path: web::Path<GetBanPayload>,
+) -> impl Responder {
+    if let Err(err) = authorize_admin(&req, &admin_token) {
         log::error!("{:?}", err);
+        return HttpResponse::Unauthorized().finish();
+    }
+    match limiter
+        .unban(operator.as_ref(), path.subject.as_str())
+        .await
+    {
+        Ok(true) => {
+            log::info!("unbanned: {}", path.subject);
+            HttpResponse::Ok().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            log::error!("{:?}", err);
+            let http_response: HttpResponse = err.into();
+            http_response
+        }
     }
-    HttpResponse::Ok().finish()
+}
+
+fn authorize_admin(req: &HttpRequest, admin_token: &AdminToken) -> Result<(), Error> {
+    let header = req.headers().get("Authorization").ok_or(Error::Internal(
+        "missing auth header in admin request".to_string(),
+    ))?;
+    let header = header.to_str()?;
+    let token = header.strip_prefix("Bearer ").unwrap_or(header);
+    if token.ne(admin_token.0.as_str()) {
+        return Err(Error::Internal(
+            "invalid auth header in admin request".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 pub async fn latest_event(
@@ -219,7 +752,7 @@ async fn update_balance_onchain(
     }
 
     // validate transaction
-    let (pool_address, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
+    let pool_address = operator.pool_address();
     tx::validate::validate_attribution(&tx, member_authority, pool_address, member.total_balance)?;
 
     // sign transaction and submit
@@ -243,11 +776,11 @@ async fn update_balance_onchain(
 
 async fn register_new_member(
     operator: &Operator,
+    directory: &MemberDirectory,
     payload: RegisterPayload,
 ) -> Result<ore_pool_types::Member, Error> {
-    let keypair = &operator.keypair;
     let member_authority = payload.authority;
-    let (pool_pda, _) = ore_pool_api::state::pool_pda(keypair.pubkey());
+    let pool_pda = operator.pool_address();
 
     // fetch db record
     let db_client = operator.db_client.get().await?;
@@ -257,7 +790,22 @@ async fn register_new_member(
     // idempotent get or create
     match db_member {
         Ok(db_member) => {
-            // member already exists in db
+            // member already exists in db,
+            // check it against the on-chain account
+            let member = operator
+                .get_member_onchain(&member_authority)
+                .await
+                .map_err(|_| Error::MemberDoesNotExist)?;
+            if database::is_stale(db_member.id, db_member.total_balance, &member) {
+                log::warn!(
+                    "resetting stale member record: {} {} {}",
+                    db_member.address,
+                    db_member.total_balance,
+                    member.total_balance
+                );
+                database::reset_member(&db_client, &db_member.address, &member).await?;
+                return database::read_member(&db_client, &db_member.address).await;
+            }
             Ok(db_member)
         }
         Err(_) => {
@@ -266,6 +814,22 @@ async fn register_new_member(
             let member = operator.get_member_onchain(&member_authority).await;
             match member {
                 Ok(member) => {
+                    // a transferred member keeps its id,
+                    // so move the record if the transfer endpoint was never called
+                    if let Some(db_member) =
+                        database::read_member_by_id(&db_client, &pool_pda, member.id).await?
+                    {
+                        return transfer_member(
+                            operator,
+                            directory,
+                            TransferMemberPayload {
+                                authority: Pubkey::from_str(db_member.authority.as_str())?,
+                                new_authority: member_authority,
+                            },
+                        )
+                        .await;
+                    }
+
                     // write member to db
                     let db_member = database::write_new_member(&db_client, &member, false).await?;
                     Ok(db_member)
@@ -283,28 +847,149 @@ async fn register_new_member(
     }
 }
 
-// TODO: consider fitting lookup table from member authority to id, in memory
-async fn validate_nonce(
+async fn leave_pool(operator: &Operator, payload: LeavePayload) -> Result<(), Error> {
+    let pool_pda = operator.pool_address();
+    let (member_pda, _) = ore_pool_api::state::member_pda(payload.authority, pool_pda);
+
+    // fetch db record
+    let db_client = operator.db_client.get().await?;
+    let db_member = database::read_member(&db_client, &member_pda.to_string()).await?;
+
+    // keep the record while it holds earnings the member account was not credited with,
+    // so they are not forfeited. the member should leave once attributed and claimed.
+    if !db_member.is_synced {
+        log::warn!(
+            "member left with unattributed balance: {} {}",
+            db_member.authority,
+            db_member.total_balance
+        );
+        return Err(Error::MemberNotSynced);
+    }
+
+    // the leave must have landed on-chain, which closes the member account
+    if operator
+        .get_member_onchain(&payload.authority)
+        .await
+        .is_ok()
+    {
+        return Err(Error::Internal(
+            "member account has not been closed on-chain".to_string(),
+        ));
+    }
+
+    // remove db record, so the member is no longer attributed.
+    // the ids of remaining members are left as is
+    database::delete_member(&db_client, &member_pda.to_string()).await
+}
+
+async fn transfer_member(
+    operator: &Operator,
+    directory: &MemberDirectory,
+    payload: TransferMemberPayload,
+) -> Result<ore_pool_types::Member, Error> {
+    let pool_pda = operator.pool_address();
+    let (member_pda, _) = ore_pool_api::state::member_pda(payload.authority, pool_pda);
+    let (new_member_pda, _) = ore_pool_api::state::member_pda(payload.new_authority, pool_pda);
+
+    // fetch db record
+    let db_client = operator.db_client.get().await?;
+    let db_member = database::read_member(&db_client, &member_pda.to_string()).await?;
+
+    // the transfer must have landed on-chain,
+    // which closes the old account and moves the record to the new authority
+    if operator
+        .get_member_onchain(&payload.authority)
+        .await
+        .is_ok()
+    {
+        return Err(Error::Internal(
+            "member account has not been transferred on-chain".to_string(),
+        ));
+    }
+    let member = operator
+        .get_member_onchain(&payload.new_authority)
+        .await
+        .map_err(|_| Error::MemberDoesNotExist)?;
+    if (member.id as i64).ne(&db_member.id) {
+        return Err(Error::Internal(
+            "transferred member id does not match db record".to_string(),
+        ));
+    }
+
+    // move db record
+    database::transfer_member(
+        &db_client,
+        &member_pda.to_string(),
+        &new_member_pda.to_string(),
+        &payload.new_authority.to_string(),
+    )
+    .await?;
+    if database::is_stale(db_member.id, db_member.total_balance, &member) {
+        database::reset_member(&db_client, &new_member_pda.to_string(), &member).await?;
+    }
+    let db_member = database::read_member(&db_client, &new_member_pda.to_string()).await?;
+
+    // move the directory entry without waiting on the notification
+    directory.remove(payload.authority.to_string().as_str());
+    directory.insert(&db_member);
+    Ok(db_member)
+}
+
+async fn build_attestation_proof(
     operator: &Operator,
+    payload: GetAttestationProofPayload,
+) -> Result<AttestationProof, Error> {
+    let member = Pubkey::from_str(payload.authority.as_str())?;
+
+    // rebuild the tree from the persisted leaves
+    let db_client = operator.db_client.get().await?;
+    let leaves = database::read_attestation_leaves(
+        &db_client,
+        &operator.pool_address(),
+        payload.last_hash_at,
+    )
+    .await?;
+    let index = leaves
+        .iter()
+        .position(|(m, _, _)| m.eq(&member))
+        .ok_or(Error::MemberDoesNotExist)?;
+    let (_, digest, nonce) = leaves[index];
+    let tree = MerkleTree::new(
+        leaves
+            .iter()
+            .map(|(m, d, n)| contribution_leaf(m, d, n))
+            .collect(),
+    );
+    let proof = tree.proof(index).ok_or(Error::MemberDoesNotExist)?;
+    Ok(AttestationProof {
+        last_hash_at: payload.last_hash_at,
+        member,
+        digest,
+        nonce,
+        attestation: tree.root(),
+        proof,
+    })
+}
+
+/// Returns whether the nonce falls within the range assigned to the member.
+/// Reads the member id from the directory, without a database round trip.
+fn validate_nonce(
+    directory: &MemberDirectory,
     member_authority: &Pubkey,
     nonce: u64,
     num_members: u64,
-) -> Result<(), Error> {
+) -> Result<bool, Error> {
     if num_members.eq(&0) {
-        return Ok(());
+        return Ok(true);
     }
-    let member = operator
-        .get_member_db(member_authority.to_string().as_str())
-        .await?;
-    let nonce_index = member.id as u64;
+    let member = directory
+        .get(member_authority)
+        .ok_or(Error::MemberDoesNotExist)?;
+    let nonce_index = member.id;
     let u64_unit = u64::MAX.saturating_div(num_members);
     let left_bound = u64_unit.saturating_mul(nonce_index);
     let right_bound = u64_unit.saturating_mul(nonce_index + 1);
     let ge_left = nonce >= left_bound;
     let le_right = nonce <= right_bound;
-    if ge_left && le_right {
-        Ok(())
-    } else {
-        Err(Error::Internal("invalid nonce from client".to_string()))
-    }
+    Ok(ge_left && le_right)
 }
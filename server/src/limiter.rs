@@ -0,0 +1,358 @@
+use std::{net::IpAddr, time::Instant};
+
+use actix_web::HttpRequest;
+use dashmap::DashMap;
+use ore_pool_types::{Ban, ContributeCode};
+
+use crate::{database, error::Error, operator::Operator};
+
+/// The window invalid contributions are counted over, in seconds.
+const OFFENCE_WINDOW: u64 = 60;
+
+/// The longest a ban escalates to, in seconds.
+const MAX_BAN_DURATION: i64 = 60 * 60 * 24 * 7;
+
+/// Rate limits the contributions of each ip address and member authority,
+/// and bans the ip addresses that keep sending invalid contributions.
+/// Ip addresses are checked before any database lookup or drillx verification.
+/// Member authorities are only charged for valid contributions and never banned automatically,
+/// since contributions are not signed.
+pub struct Limiter {
+    /// The tokens refilled per second.
+    rate: f64,
+
+    /// The max tokens a bucket holds.
+    burst: f64,
+
+    /// The invalid contributions tolerated per window before a ban.
+    ban_threshold: u32,
+
+    /// The duration of a first ban in seconds, doubled with every repeat offence.
+    ban_duration: i64,
+
+    /// The token bucket of each subject.
+    buckets: DashMap<String, TokenBucket>,
+
+    /// The invalid contributions of each subject in the current window.
+    offences: DashMap<String, Offences>,
+
+    /// The bans of each subject, kept once expired to escalate repeat offences.
+    bans: DashMap<String, Ban>,
+
+    /// The proxies trusted to forward the client ip address in a header.
+    trusted_proxies: Vec<IpAddr>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Offences {
+    count: u32,
+    window_start: Instant,
+}
+
+/// Whether the rejection counts towards a ban.
+/// Stale and duplicate contributions are expected from honest miners around a reset,
+/// and solutions below the min difficulty whenever it is raised.
+pub fn is_offence(code: ContributeCode) -> bool {
+    matches!(
+        code,
+        ContributeCode::InvalidDigest
+            | ContributeCode::NonceOutOfRange
+            | ContributeCode::UnknownMember
+    )
+}
+
+impl Limiter {
+    /// builds the limiter with the bans recorded in the database
+    pub async fn new(
+        operator: &Operator,
+        rate_per_minute: u64,
+        burst: u64,
+        ban_threshold: u32,
+        ban_duration: i64,
+        trusted_proxies: Vec<IpAddr>,
+    ) -> Result<Self, Error> {
+        let db_client = operator.db_client.get().await?;
+        let bans = database::read_bans(&db_client, &operator.pool_address()).await?;
+        Ok(Self::with_bans(
+            rate_per_minute,
+            burst,
+            ban_threshold,
+            ban_duration,
+            trusted_proxies,
+            bans,
+        ))
+    }
+
+    fn with_bans(
+        rate_per_minute: u64,
+        burst: u64,
+        ban_threshold: u32,
+        ban_duration: i64,
+        trusted_proxies: Vec<IpAddr>,
+        bans: Vec<Ban>,
+    ) -> Self {
+        Self {
+            rate: rate_per_minute as f64 / 60.0,
+            burst: burst as f64,
+            ban_threshold,
+            ban_duration,
+            buckets: DashMap::new(),
+            offences: DashMap::new(),
+            bans: bans
+                .into_iter()
+                .map(|ban| (ban.subject.clone(), ban))
+                .collect(),
+            trusted_proxies,
+        }
+    }
+
+    /// the ip address of the client,
+    /// read from the forwarding headers only if the peer is a trusted proxy
+    pub fn client_ip(&self, req: &HttpRequest) -> String {
+        let Some(peer) = req.peer_addr() else {
+            return "unknown".to_string();
+        };
+        if self.trusted_proxies.contains(&peer.ip()) {
+            if let Some(ip) = req.connection_info().realip_remote_addr() {
+                return ip.to_string();
+            }
+        }
+        peer.ip().to_string()
+    }
+
+    /// the active ban on the subject, if any
+    pub fn banned(&self, subject: &str) -> Option<Ban> {
+        self.bans
+            .get(subject)
+            .filter(|ban| ban.expires_at > crate::unix_timestamp())
+            .map(|ban| ban.clone())
+    }
+
+    /// takes a token from the bucket of the subject,
+    /// returns false if the bucket is empty
+    pub fn allow(&self, subject: &str) -> bool {
+        self.allow_at(subject, Instant::now())
+    }
+
+    fn allow_at(&self, subject: &str, now: Instant) -> bool {
+        let mut bucket = self
+            .buckets
+            .entry(subject.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: self.burst,
+                updated_at: now,
+            });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// counts an invalid contribution against the subject,
+    /// and bans the subject once it exceeds the threshold in the window
+    pub async fn offend(
+        &self,
+        operator: &Operator,
+        subject: &str,
+        code: ContributeCode,
+    ) -> Result<Option<Ban>, Error> {
+        let Some(ban) = self.count_offence(subject, code)? else {
+            return Ok(None);
+        };
+        let db_client = operator.db_client.get().await?;
+        database::write_ban(&db_client, &operator.pool_address(), &ban).await?;
+        Ok(Some(ban))
+    }
+
+    /// counts the offence in memory and enforces the ban once the threshold is exceeded,
+    /// before it is persisted so a database error does not lift the ban
+    fn count_offence(&self, subject: &str, code: ContributeCode) -> Result<Option<Ban>, Error> {
+        // count the offence in the current window
+        let exceeded = {
+            let now = Instant::now();
+            let mut offences = self
+                .offences
+                .entry(subject.to_string())
+                .or_insert_with(|| Offences {
+                    count: 0,
+                    window_start: now,
+                });
+            if now.duration_since(offences.window_start).as_secs() >= OFFENCE_WINDOW {
+                offences.count = 0;
+                offences.window_start = now;
+            }
+            offences.count += 1;
+            offences.count > self.ban_threshold
+        };
+        if !exceeded {
+            return Ok(None);
+        }
+        self.offences.remove(subject);
+
+        // escalate the ban duration with every repeat offence
+        let offences = self
+            .bans
+            .get(subject)
+            .map(|ban| ban.offences)
+            .unwrap_or(0)
+            + 1;
+        let duration = self
+            .ban_duration
+            .saturating_mul(1i64 << (offences - 1).min(32))
+            .min(MAX_BAN_DURATION);
+        let banned_at = crate::unix_timestamp();
+        let ban = Ban {
+            subject: subject.to_string(),
+            reason: serde_json::to_value(code)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            offences,
+            banned_at,
+            expires_at: banned_at + duration,
+        };
+        log::warn!("banned: {:?}", ban);
+        self.bans.insert(ban.subject.clone(), ban.clone());
+        Ok(Some(ban))
+    }
+
+    /// lifts the ban on the subject and forgets its offences.
+    /// returns false if the subject was never banned.
+    pub async fn unban(&self, operator: &Operator, subject: &str) -> Result<bool, Error> {
+        let db_client = operator.db_client.get().await?;
+        let deleted = database::delete_ban(&db_client, &operator.pool_address(), subject).await?;
+        let removed = self.bans.remove(subject).is_some();
+        self.offences.remove(subject);
+        self.buckets.remove(subject);
+        Ok(deleted || removed)
+    }
+
+    /// drops the buckets that have refilled and the offences that fell out of the window,
+    /// so the maps only hold recently active subjects
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+            bucket.tokens + elapsed * self.rate < self.burst
+        });
+        self.offences.retain(|_, offences| {
+            now.duration_since(offences.window_start).as_secs() < OFFENCE_WINDOW
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn limiter(trusted_proxies: Vec<IpAddr>) -> Limiter {
+        // 1 token per second up to 2, banned past 2 offences for a minute
+        Limiter::with_bans(60, 2, 2, 60, trusted_proxies, vec![])
+    }
+
+    #[test]
+    fn allow_refills_the_bucket_over_time() {
+        let limiter = limiter(vec![]);
+        let now = Instant::now();
+        assert!(limiter.allow_at("ip", now));
+        assert!(limiter.allow_at("ip", now));
+        assert!(!limiter.allow_at("ip", now));
+
+        // other subjects have their own bucket
+        assert!(limiter.allow_at("other", now));
+
+        // refilled at the rate, up to the burst
+        let now = now + Duration::from_secs(1);
+        assert!(limiter.allow_at("ip", now));
+        assert!(!limiter.allow_at("ip", now));
+        let now = now + Duration::from_secs(60);
+        assert!(limiter.allow_at("ip", now));
+        assert!(limiter.allow_at("ip", now));
+        assert!(!limiter.allow_at("ip", now));
+    }
+
+    #[test]
+    fn offences_escalate_bans() {
+        let limiter = limiter(vec![]);
+        let code = ContributeCode::InvalidDigest;
+
+        // tolerated up to the threshold
+        assert!(limiter.count_offence("ip", code).unwrap().is_none());
+        assert!(limiter.count_offence("ip", code).unwrap().is_none());
+        assert!(limiter.banned("ip").is_none());
+        let ban = limiter.count_offence("ip", code).unwrap().unwrap();
+        assert_eq!(ban.offences, 1);
+        assert_eq!(ban.reason, "invalid_digest");
+        assert_eq!(ban.expires_at - ban.banned_at, 60);
+        assert!(limiter.banned("ip").is_some());
+        assert!(limiter.banned("other").is_none());
+
+        // doubled with every repeat offence
+        for offences in 2..=3 {
+            assert!(limiter.count_offence("ip", code).unwrap().is_none());
+            assert!(limiter.count_offence("ip", code).unwrap().is_none());
+            let ban = limiter.count_offence("ip", code).unwrap().unwrap();
+            assert_eq!(ban.offences, offences);
+            assert_eq!(ban.expires_at - ban.banned_at, 60 << (offences - 1));
+        }
+    }
+
+    #[test]
+    fn bans_escalate_up_to_a_week() {
+        let ban = Ban {
+            subject: "ip".to_string(),
+            reason: "invalid_digest".to_string(),
+            offences: 40,
+            banned_at: 0,
+            expires_at: 0,
+        };
+        let limiter = Limiter::with_bans(60, 2, 0, 60, vec![], vec![ban]);
+        assert!(limiter.banned("ip").is_none());
+        let ban = limiter
+            .count_offence("ip", ContributeCode::UnknownMember)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ban.offences, 41);
+        assert_eq!(ban.expires_at - ban.banned_at, MAX_BAN_DURATION);
+    }
+
+    #[test]
+    fn is_offence_skips_rejections_expected_from_honest_miners() {
+        assert!(!is_offence(ContributeCode::BelowMinDifficulty));
+        assert!(!is_offence(ContributeCode::StaleChallenge));
+        assert!(!is_offence(ContributeCode::Duplicate));
+        assert!(is_offence(ContributeCode::InvalidDigest));
+        assert!(is_offence(ContributeCode::NonceOutOfRange));
+        assert!(is_offence(ContributeCode::UnknownMember));
+    }
+
+    #[test]
+    fn client_ip_reads_forwarding_headers_from_trusted_proxies_only() {
+        let req = |peer: &str| {
+            TestRequest::default()
+                .peer_addr(format!("{}:8080", peer).parse().unwrap())
+                .insert_header(("X-Forwarded-For", "1.2.3.4"))
+                .to_http_request()
+        };
+        let limiter = limiter(vec!["10.0.0.1".parse().unwrap()]);
+        assert_eq!(limiter.client_ip(&req("10.0.0.1")), "1.2.3.4");
+        assert_eq!(limiter.client_ip(&req("10.0.0.2")), "10.0.0.2");
+        assert_eq!(
+            limiter.client_ip(&TestRequest::default().to_http_request()),
+            "unknown"
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use std::{collections::HashMap, str::FromStr};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures::StreamExt;
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+
+use crate::{contributions::PoolMiningEvent, database, error::Error, operator::Operator};
+
+/// Where the mining events of the pool are sourced from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventSource {
+    /// Helius POSTs each transaction to the webhook route.
+    Webhook,
+
+    /// The server subscribes to the logs of the pool proof over Solana pubsub.
+    Pubsub,
+
+    /// The server polls the signatures of the pool proof over RPC.
+    Poll,
+}
+
+impl FromStr for EventSource {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "webhook" => Ok(EventSource::Webhook),
+            "pubsub" => Ok(EventSource::Pubsub),
+            "poll" => Ok(EventSource::Poll),
+            _ => Err(Error::Internal(format!("invalid event source: {}", s))),
+        }
+    }
+}
+
+/// The max number of signatures returned per request by the RPC.
+const SIGNATURES_PAGE_SIZE: usize = 1_000;
+
+/// Fetches and verifies the mining event of a transaction.
+/// Implemented by the operator over RPC, and by mocks in tests.
+#[async_trait::async_trait]
+pub trait MineEventFetcher: Send + Sync {
+    /// Returns the slot, block time and event, or none if the transaction did not mine.
+    async fn fetch_mine_event(
+        &self,
+        signature: &Signature,
+        source: &str,
+    ) -> Result<Option<(u64, u64, ore_api::event::MineEvent)>, Error>;
+}
+
+#[async_trait::async_trait]
+impl MineEventFetcher for Operator {
+    async fn fetch_mine_event(
+        &self,
+        signature: &Signature,
+        source: &str,
+    ) -> Result<Option<(u64, u64, ore_api::event::MineEvent)>, Error> {
+        fetch_mine_event(self, signature, source).await
+    }
+}
+
+/// Subscribes to the transaction logs that mention the pool proof,
+/// and forwards every mining event to the aggregator.
+/// Returns once the subscription closes, for the caller to reconnect.
+pub async fn subscribe(
+    fetcher: &dyn MineEventFetcher,
+    proof_address: &Pubkey,
+    ws_url: &str,
+    tx: &tokio::sync::mpsc::Sender<PoolMiningEvent>,
+) -> Result<(), Error> {
+    let pubsub_client = PubsubClient::new(ws_url).await?;
+    let (mut stream, unsubscribe) = pubsub_client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![proof_address.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?;
+    log::info!("subscribed to proof logs: {}", ws_url);
+    while let Some(response) = stream.next().await {
+        let logs = response.value;
+        // skip failed transactions
+        if logs.err.is_some() {
+            continue;
+        }
+        // skip transactions that did not mine, e.g. attributions
+        if parse_mine_event(logs.logs.as_slice()).is_err() {
+            continue;
+        }
+        // the notification carries no account keys,
+        // so fetch the transaction to verify it before forwarding
+        let signature = match Signature::from_str(logs.signature.as_str()) {
+            Ok(signature) => signature,
+            Err(err) => {
+                log::error!("invalid signature: {} {:?}", logs.signature, err);
+                continue;
+            }
+        };
+        match fetcher.fetch_mine_event(&signature, "pubsub").await {
+            Ok(Some((slot, timestamp, mine_event))) => {
+                send(tx, signature, slot, timestamp, mine_event).await?;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                // left for the backfill to pick up
+                log::error!("{:?}", err);
+            }
+        }
+    }
+    unsubscribe().await;
+    Err(Error::Internal("proof log subscription closed".to_string()))
+}
+
+/// Polls the signatures of the pool proof every interval,
+/// and forwards every mining event to the aggregator.
+/// Only events after startup are forwarded.
+pub async fn poll(
+    rpc_client: &RpcClient,
+    fetcher: &dyn MineEventFetcher,
+    proof_address: &Pubkey,
+    interval: u64,
+    tx: &tokio::sync::mpsc::Sender<PoolMiningEvent>,
+) -> Result<(), Error> {
+    // start from the most recent signature
+    let mut until = rpc_client
+        .get_signatures_for_address_with_config(
+            proof_address,
+            GetConfirmedSignaturesForAddress2Config {
+                before: None,
+                until: None,
+                limit: Some(1),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?
+        .first()
+        .map(|status| Signature::from_str(status.signature.as_str()))
+        .transpose()?;
+    loop {
+        tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+        let signatures = match signatures_until(rpc_client, proof_address, until).await {
+            Ok(signatures) => signatures,
+            Err(err) => {
+                log::error!("{:?}", err);
+                continue;
+            }
+        };
+        // signatures are returned newest first
+        for status in signatures.iter().rev() {
+            let signature = match Signature::from_str(status.signature.as_str()) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    log::error!("invalid signature: {} {:?}", status.signature, err);
+                    continue;
+                }
+            };
+            if status.err.is_none() {
+                match fetcher.fetch_mine_event(&signature, "poll").await {
+                    Ok(Some((slot, timestamp, mine_event))) => {
+                        send(tx, signature, slot, timestamp, mine_event).await?;
+                    }
+                    Ok(None) => {}
+                    Err(err) => {
+                        // retry from this signature on the next poll
+                        log::error!("{:?}", err);
+                        break;
+                    }
+                }
+            }
+            // advance past the processed signature
+            until = Some(signature);
+        }
+    }
+}
+
+/// Reads every signature of the address after the until signature, newest first,
+/// paging back through the history since each request returns at most a page.
+pub async fn signatures_until(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+    until: Option<Signature>,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, Error> {
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = rpc_client
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(SIGNATURES_PAGE_SIZE),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await?;
+        let full = page.len() >= SIGNATURES_PAGE_SIZE;
+        let oldest = page.last().map(|status| status.signature.clone());
+        signatures.extend(page);
+        match oldest {
+            Some(oldest) if full => before = Some(Signature::from_str(oldest.as_str())?),
+            _ => return Ok(signatures),
+        }
+    }
+}
+
+/// Fetches a transaction and parses its mining event.
+/// Returns the slot, block time and event, or none if the transaction did not mine.
+///
+/// The transaction must have succeeded and its account keys must include the ore program,
+/// the pool program, the pool and the pool proof. Otherwise the event is quarantined
+/// instead of returned, so it never credits balances.
+pub async fn fetch_mine_event(
+    operator: &Operator,
+    signature: &Signature,
+    source: &str,
+) -> Result<Option<(u64, u64, ore_api::event::MineEvent)>, Error> {
+    let transaction = operator
+        .rpc_client
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await?;
+    let Some(meta) = transaction.transaction.meta else {
+        return Ok(None);
+    };
+    let OptionSerializer::Some(log_messages) = meta.log_messages else {
+        return Ok(None);
+    };
+    let Ok(mine_event) = parse_mine_event(log_messages.as_slice()) else {
+        return Ok(None);
+    };
+
+    // verify the event belongs to this pool
+    let mut account_keys: Vec<String> = match transaction.transaction.transaction.decode() {
+        Some(versioned_transaction) => versioned_transaction
+            .message
+            .static_account_keys()
+            .iter()
+            .map(|key| key.to_string())
+            .collect(),
+        None => vec![],
+    };
+    if let OptionSerializer::Some(loaded_addresses) = meta.loaded_addresses {
+        account_keys.extend(loaded_addresses.writable);
+        account_keys.extend(loaded_addresses.readonly);
+    }
+    let reason = if meta.err.is_some() {
+        Some("transaction failed".to_string())
+    } else {
+        [
+            ("ore program", ore_api::ID),
+            ("pool program", ore_pool_api::ID),
+            ("pool", operator.pool_address()),
+            ("pool proof", operator.proof_address()),
+        ]
+        .into_iter()
+        .find(|(_, key)| !account_keys.contains(&key.to_string()))
+        .map(|(name, key)| format!("transaction missing {}: {}", name, key))
+    };
+    if let Some(reason) = reason {
+        quarantine(operator, signature, source, reason.as_str(), &mine_event).await?;
+        return Ok(None);
+    }
+
+    let timestamp = transaction.block_time.unwrap_or_else(crate::unix_timestamp);
+    Ok(Some((transaction.slot, timestamp as u64, mine_event)))
+}
+
+/// Records a mining event that failed verification, instead of crediting balances.
+pub async fn quarantine(
+    operator: &Operator,
+    signature: &Signature,
+    source: &str,
+    reason: &str,
+    mine_event: &ore_api::event::MineEvent,
+) -> Result<(), Error> {
+    log::warn!(
+        "quarantined mining event from {}: {} {}",
+        source,
+        signature,
+        reason
+    );
+    let db_client = operator.db_client.get().await?;
+    database::write_quarantined_event(
+        &db_client,
+        &operator.pool_address(),
+        signature,
+        source,
+        reason,
+        mine_event,
+    )
+    .await
+}
+
+/// Parse a MineEvent from the return data in the transaction logs
+pub fn parse_mine_event(log_messages: &[String]) -> Result<ore_api::event::MineEvent, Error> {
+    // Find return data string
+    let prefix = format!("Program return: {} ", ore_pool_api::ID);
+    let mine_event_str = log_messages
+        .iter()
+        .rev()
+        .find_map(|log_message| log_message.strip_prefix(prefix.as_str()))
+        .ok_or(Error::Internal(
+            "transaction logs missing return data".to_string(),
+        ))?;
+
+    // Parse return data
+    let mine_event = BASE64_STANDARD.decode(mine_event_str)?;
+    let mine_event: ore_api::event::MineEvent =
+        bytemuck::try_pod_read_unaligned(mine_event.as_slice())
+            .map_err(|e| Error::Internal(e.to_string()))?;
+    Ok(mine_event)
+}
+
+pub fn pool_mining_event(
+    signature: Signature,
+    block: u64,
+    timestamp: u64,
+    mine_event: ore_api::event::MineEvent,
+) -> PoolMiningEvent {
+    PoolMiningEvent {
+        signature,
+        block,
+        timestamp,
+        mine_event,
+        member_rewards: HashMap::new(),
+        member_scores: HashMap::new(),
+    }
+}
+
+async fn send(
+    tx: &tokio::sync::mpsc::Sender<PoolMiningEvent>,
+    signature: Signature,
+    block: u64,
+    timestamp: u64,
+    mine_event: ore_api::event::MineEvent,
+) -> Result<(), Error> {
+    let event = pool_mining_event(signature, block, timestamp, mine_event);
+    tx.send(event)
+        .await
+        .map_err(|err| Error::Internal(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+    use futures::SinkExt;
+    use ore_api::event::MineEvent;
+    use serde_json::json;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+
+    /// Returns the same mining event for every transaction, without RPC or a database.
+    struct MockFetcher;
+
+    #[async_trait::async_trait]
+    impl MineEventFetcher for MockFetcher {
+        async fn fetch_mine_event(
+            &self,
+            _signature: &Signature,
+            _source: &str,
+        ) -> Result<Option<(u64, u64, MineEvent)>, Error> {
+            Ok(Some((5, 10, mine_event())))
+        }
+    }
+
+    fn mine_event() -> MineEvent {
+        let mut mine_event = MineEvent::zeroed();
+        mine_event.last_hash_at = 100;
+        mine_event
+    }
+
+    fn mine_logs() -> Vec<String> {
+        vec![format!(
+            "Program return: {} {}",
+            ore_pool_api::ID,
+            BASE64_STANDARD.encode(bytemuck::bytes_of(&mine_event()))
+        )]
+    }
+
+    fn notification(signature: &str, err: Option<&str>, logs: Vec<String>) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "logsNotification",
+            "params": {
+                "result": {
+                    "context": { "slot": 5 },
+                    "value": { "signature": signature, "err": err, "logs": logs }
+                },
+                "subscription": 1
+            }
+        })
+        .to_string()
+    }
+
+    /// Serves a single logs subscription on a local port,
+    /// sends the notifications and closes the connection.
+    async fn mock_pubsub(notifications: Vec<String>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            // confirm the subscription
+            while let Some(Ok(message)) = ws.next().await {
+                if let Message::Text(text) = message {
+                    let request: serde_json::Value = serde_json::from_str(text.as_str()).unwrap();
+                    assert_eq!(request["method"], "logsSubscribe");
+                    let response = json!({ "jsonrpc": "2.0", "result": 1, "id": request["id"] });
+                    ws.send(Message::Text(response.to_string())).await.unwrap();
+                    break;
+                }
+            }
+            for notification in notifications {
+                ws.send(Message::Text(notification)).await.unwrap();
+            }
+            ws.close(None).await.unwrap();
+        });
+        url
+    }
+
+    #[actix_web::test]
+    async fn subscribe_forwards_mining_events() {
+        let signature = Signature::new_unique();
+        let url = mock_pubsub(vec![
+            // skipped, without closing the subscription
+            notification("not a signature", None, mine_logs()),
+            // failed
+            notification(
+                Signature::new_unique().to_string().as_str(),
+                Some("AccountInUse"),
+                mine_logs(),
+            ),
+            // did not mine
+            notification(Signature::new_unique().to_string().as_str(), None, vec![]),
+            notification(signature.to_string().as_str(), None, mine_logs()),
+        ])
+        .await;
+        let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+        let res = subscribe(&MockFetcher, &Pubkey::new_unique(), url.as_str(), &tx).await;
+
+        // returns once the connection closes, for the caller to reconnect
+        assert!(res.is_err());
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.signature, signature);
+        assert_eq!(event.block, 5);
+        assert_eq!(event.timestamp, 10);
+        assert_eq!(event.mine_event.last_hash_at, 100);
+        assert!(rx.try_recv().is_err());
+    }
+}
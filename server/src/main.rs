@@ -1,9 +1,17 @@
 mod aggregator;
+mod backfill;
+mod balances;
 mod contributions;
 mod database;
+mod directory;
 mod error;
 mod handlers;
+mod limiter;
+mod listener;
 mod operator;
+mod publisher;
+mod reward_policy;
+mod round;
 mod tx;
 mod utils;
 mod webhook;
@@ -12,26 +20,36 @@ use core::panic;
 
 use actix_web::{get, middleware, web, App, HttpResponse, HttpServer, Responder};
 use aggregator::Aggregator;
+use balances::{BalanceMode, BalanceTree};
 use contributions::{Contribution, PoolMiningEvent};
+use directory::MemberDirectory;
+use limiter::Limiter;
+use listener::EventSource;
 use operator::Operator;
+use ore_pool_types::ContributionRecord;
 use utils::create_cors;
 
-// TODO: publish attestation to s3
-// write attestation url to db with last-hash-at as foreign key
+const CONTRIBUTION_RECORDS_PER_WRITE: usize = 1_000;
+const SHUTDOWN_DRAIN_TIMEOUT: u64 = 30;
+
 #[actix_web::main]
 async fn main() -> Result<(), error::Error> {
     env_logger::init();
     // env vars
     let attribution_epoch = attribution_epoch()?;
+    let balance_mode = balance_mode()?;
+    let contributions_retention = contributions_retention()?;
+    let snapshot_interval = snapshot_interval()?;
+    let event_source = event_source()?;
+    let backfill_interval = backfill_interval()?;
 
     // events channel
     let (events_tx, mut events_rx) = tokio::sync::mpsc::channel::<PoolMiningEvent>(1);
     let events_tx = web::Data::new(events_tx);
 
-    // contributions channel
-    let (contributions_tx, mut contributions_rx) =
-        tokio::sync::mpsc::unbounded_channel::<Contribution>();
-    let contributions_tx = web::Data::new(contributions_tx);
+    // contribution records channel
+    let (records_tx, mut records_rx) = tokio::sync::mpsc::unbounded_channel::<ContributionRecord>();
+    let records_tx = web::Data::new(records_tx);
 
     // clock channel
     let (clock_tx, _) = tokio::sync::broadcast::channel::<i64>(1);
@@ -41,23 +59,140 @@ async fn main() -> Result<(), error::Error> {
     let operator = web::Data::new(Operator::new()?);
     let aggregator = web::Data::new(tokio::sync::RwLock::new(Aggregator::new(&operator).await?));
 
-    // aggregate contributions
+    // current round, shared with the handlers to insert contributions without the aggregator lock
+    let round = web::Data::from(aggregator.read().await.round.clone());
+
+    // current challenge, shared with the handlers to serve it without the aggregator lock
+    let challenge_rx = web::Data::new(aggregator.read().await.challenge_tx.subscribe());
+
+    // member ids for nonce validation, kept in sync with the members table
+    let directory = web::Data::new(MemberDirectory::new(&operator).await?);
+
+    // rate limits and bans on contributors
+    let limiter = web::Data::new(
+        Limiter::new(
+            &operator,
+            rate_limit()?,
+            rate_limit_burst()?,
+            ban_threshold()?,
+            60 * ban_duration()?,
+            trusted_proxies()?,
+        )
+        .await?,
+    );
+    let admin_token = admin_token().map(|token| web::Data::new(handlers::AdminToken(token)));
+
+    // balance tree backing the posted balance root
+    let balance_tree = web::Data::new(tokio::sync::RwLock::new(BalanceTree::default()));
+
+    // submit contributions
     tokio::task::spawn({
         let operator = operator.clone();
         let aggregator = aggregator.clone();
         async move {
-            if let Err(err) = aggregator::process_contributions(
-                aggregator.as_ref(),
-                operator.as_ref(),
-                &mut contributions_rx,
-            )
-            .await
+            if let Err(err) =
+                aggregator::process_contributions(aggregator.as_ref(), operator.as_ref()).await
             {
                 log::error!("{:?}", err);
             }
         }
     });
 
+    // persist contribution records in batches
+    let records_handle = tokio::task::spawn({
+        let operator = operator.clone();
+        async move {
+            let mut buffer = Vec::with_capacity(CONTRIBUTION_RECORDS_PER_WRITE);
+            while records_rx
+                .recv_many(&mut buffer, CONTRIBUTION_RECORDS_PER_WRITE)
+                .await
+                > 0
+            {
+                match operator.db_client.get().await {
+                    Ok(db_client) => {
+                        if let Err(err) = database::write_contributions(
+                            &db_client,
+                            &operator.pool_address(),
+                            buffer.as_slice(),
+                        )
+                        .await
+                        {
+                            log::error!("{:?}", err);
+                        }
+                    }
+                    Err(err) => log::error!("{:?}", err),
+                }
+                buffer.clear();
+            }
+        }
+    });
+
+    // checkpoint aggregator
+    tokio::task::spawn({
+        let operator = operator.clone();
+        let aggregator = aggregator.clone();
+        async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_secs(snapshot_interval)).await;
+                let mut aggregator = aggregator.write().await;
+                if let Err(err) = aggregator.checkpoint(operator.as_ref()).await {
+                    log::error!("{:?}", err);
+                }
+            }
+        }
+    });
+
+    // listen for member changes, reconnecting if the connection drops
+    tokio::task::spawn({
+        let operator = operator.clone();
+        let directory = directory.clone();
+        async move {
+            loop {
+                if let Err(err) = directory::listen(directory.as_ref(), operator.as_ref()).await {
+                    log::error!("{:?}", err);
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            }
+        }
+    });
+
+    // prune idle rate limits
+    tokio::task::spawn({
+        let limiter = limiter.clone();
+        async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+                limiter.prune();
+            }
+        }
+    });
+
+    // prune contribution records past retention
+    tokio::task::spawn({
+        let operator = operator.clone();
+        async move {
+            loop {
+                let cutoff = unix_timestamp() - 60 * 60 * 24 * contributions_retention;
+                match operator.db_client.get().await {
+                    Ok(db_client) => {
+                        match database::prune_contributions(
+                            &db_client,
+                            &operator.pool_address(),
+                            cutoff,
+                        )
+                        .await
+                        {
+                            Ok(count) => log::info!("pruned contributions: {}", count),
+                            Err(err) => log::error!("{:?}", err),
+                        }
+                    }
+                    Err(err) => log::error!("{:?}", err),
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(60 * 60)).await;
+            }
+        }
+    });
+
     // distribute rewards
     tokio::task::spawn({
         let operator = operator.clone();
@@ -82,15 +217,100 @@ async fn main() -> Result<(), error::Error> {
         }
     });
 
+    // webhook provider, if mining events are pushed to the server
+    let webhook_provider = match event_source {
+        EventSource::Webhook => Some(web::Data::new(webhook::from_env()?)),
+        _ => None,
+    };
+
+    // listen for mining events
+    match event_source {
+        EventSource::Webhook => {}
+        EventSource::Pubsub => {
+            let ws_url = ws_url()?;
+            tokio::task::spawn({
+                let operator = operator.clone();
+                let events_tx = events_tx.clone();
+                async move {
+                    loop {
+                        if let Err(err) = listener::subscribe(
+                            operator.as_ref(),
+                            &operator.proof_address(),
+                            ws_url.as_str(),
+                            &events_tx,
+                        )
+                        .await
+                        {
+                            log::error!("{:?}", err);
+                        }
+                        // reconnect
+                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    }
+                }
+            });
+        }
+        EventSource::Poll => {
+            let poll_interval = poll_interval()?;
+            tokio::task::spawn({
+                let operator = operator.clone();
+                let events_tx = events_tx.clone();
+                async move {
+                    loop {
+                        if let Err(err) = listener::poll(
+                            &operator.rpc_client,
+                            operator.as_ref(),
+                            &operator.proof_address(),
+                            poll_interval,
+                            &events_tx,
+                        )
+                        .await
+                        {
+                            log::error!("{:?}", err);
+                        }
+                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    }
+                }
+            });
+        }
+    }
+
+    // replay mining events missed while down, then keep reconciling
+    tokio::task::spawn({
+        let operator = operator.clone();
+        let aggregator = aggregator.clone();
+        async move {
+            loop {
+                match backfill::backfill(operator.as_ref(), aggregator.as_ref()).await {
+                    Ok(count) => log::info!("backfilled mining events: {}", count),
+                    Err(err) => log::error!("{:?}", err),
+                }
+                tokio::time::sleep(tokio::time::Duration::from_secs(60 * backfill_interval)).await;
+            }
+        }
+    });
+
     // kick off attribution loop
     tokio::task::spawn({
         let operator = operator.clone();
+        let balance_tree = balance_tree.clone();
         async move {
             loop {
-                // submit attributions
                 let operator = operator.clone().into_inner();
-                if let Err(err) = operator.attribute_members().await {
-                    panic!("{:?}", err)
+                match balance_mode {
+                    BalanceMode::Attribute => {
+                        // submit attributions
+                        if let Err(err) = operator.attribute_members().await {
+                            panic!("{:?}", err)
+                        }
+                    }
+                    BalanceMode::Merkle => {
+                        // post balance root
+                        // and swap in the tree for serving proofs
+                        match operator.post_balance_root().await {
+                            Ok(tree) => *balance_tree.write().await = tree,
+                            Err(err) => log::error!("{:?}", err),
+                        }
+                    }
                 }
                 // sleep until next epoch
                 tokio::time::sleep(tokio::time::Duration::from_secs(60 * attribution_epoch)).await;
@@ -130,37 +350,107 @@ async fn main() -> Result<(), error::Error> {
         }
     });
 
+    // handles for the final checkpoint once the server exits
+    let checkpoint_operator = operator.clone();
+    let checkpoint_aggregator = aggregator.clone();
+
     // launch server
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         log::info!("starting pool server");
         App::new()
             .wrap(middleware::Logger::default())
             .wrap(create_cors())
-            .app_data(contributions_tx.clone())
+            .app_data(round.clone())
+            .app_data(challenge_rx.clone())
+            .app_data(records_tx.clone())
+            .app_data(directory.clone())
+            .app_data(limiter.clone())
             .app_data(clock_tx.clone())
             .app_data(operator.clone())
             .app_data(aggregator.clone())
             .app_data(events_tx.clone())
+            .app_data(balance_tree.clone())
             .service(web::resource("/address").route(web::get().to(handlers::address)))
+            .service(
+                web::resource("/attestation/{last_hash_at}/proof/{authority}")
+                    .route(web::get().to(handlers::attestation_proof)),
+            )
             .service(web::resource("/challenge").route(web::get().to(handlers::challenge)))
+            .service(
+                web::resource("/challenge/stream").route(web::get().to(handlers::challenge_stream)),
+            )
             .service(
                 web::resource("/challenge/{authority}").route(web::get().to(handlers::challenge)),
             )
+            .service(
+                web::resource("/contributions/{last_hash_at}")
+                    .route(web::get().to(handlers::challenge_contributions)),
+            )
             .service(web::resource("/contribute").route(web::post().to(handlers::contribute)))
             .service(web::resource("/commit").route(web::post().to(handlers::commit_balance)))
+            .service(web::resource("/events").route(web::get().to(handlers::events)))
             .service(
                 web::resource("/event/latest/{authority}")
                     .route(web::get().to(handlers::latest_event)),
             )
             .service(web::resource("/member/{authority}").route(web::get().to(handlers::member)))
+            .service(
+                web::resource("/member/{authority}/contributions")
+                    .route(web::get().to(handlers::member_contributions)),
+            )
+            .service(
+                web::resource("/member/{authority}/events")
+                    .route(web::get().to(handlers::member_events)),
+            )
+            .service(
+                web::resource("/member/{authority}/proof")
+                    .route(web::get().to(handlers::member_proof)),
+            )
             .service(web::resource("/register").route(web::post().to(handlers::register)))
-            .service(web::resource("/webhook/rewards").route(web::post().to(webhook::mine_event)))
+            .service(web::resource("/transfer").route(web::post().to(handlers::transfer)))
+            .service(web::resource("/leave").route(web::post().to(handlers::leave)))
+            .configure(|cfg| {
+                if let Some(webhook_provider) = webhook_provider.clone() {
+                    cfg.app_data(webhook_provider).service(
+                        web::resource("/webhook/rewards")
+                            .route(web::post().to(webhook::mine_event)),
+                    );
+                }
+            })
+            .configure(|cfg| {
+                if let Some(admin_token) = admin_token.clone() {
+                    cfg.app_data(admin_token)
+                        .service(web::resource("/admin/bans").route(web::get().to(handlers::bans)))
+                        .service(
+                            web::resource("/admin/bans/{subject}")
+                                .route(web::delete().to(handlers::unban)),
+                        );
+                }
+            })
             .service(health)
     })
     .bind("0.0.0.0:3000")?
     .run()
-    .await
-    .map_err(From::from)
+    .await;
+
+    // the server has stopped accepting requests (e.g. on SIGTERM),
+    // so the records channel closes once the workers are dropped.
+    // drain the in-flight records before the final checkpoint,
+    // which waits out any submission in progress for the aggregator lock.
+    log::info!("draining aggregator");
+    let timeout = tokio::time::Duration::from_secs(SHUTDOWN_DRAIN_TIMEOUT);
+    if tokio::time::timeout(timeout, records_handle).await.is_err() {
+        log::error!("timed out draining contribution records");
+    }
+    if let Err(err) = checkpoint_aggregator
+        .write()
+        .await
+        .checkpoint(checkpoint_operator.as_ref())
+        .await
+    {
+        log::error!("{:?}", err);
+    }
+    server.map_err(From::from)
 }
 
 // denominated in minutes
@@ -170,6 +460,135 @@ fn attribution_epoch() -> Result<u64, error::Error> {
     Ok(epoch)
 }
 
+// denominated in days, defaults to 30
+fn contributions_retention() -> Result<i64, error::Error> {
+    match std::env::var("CONTRIBUTIONS_RETENTION") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(30),
+    }
+}
+
+// denominated in seconds, defaults to 10
+fn snapshot_interval() -> Result<u64, error::Error> {
+    match std::env::var("SNAPSHOT_INTERVAL") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(10),
+    }
+}
+
+// contributions per minute per member authority and per ip address, defaults to 120
+fn rate_limit() -> Result<u64, error::Error> {
+    match std::env::var("RATE_LIMIT") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(120),
+    }
+}
+
+// contributions allowed in a burst above the rate limit, defaults to 20
+fn rate_limit_burst() -> Result<u64, error::Error> {
+    match std::env::var("RATE_LIMIT_BURST") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(20),
+    }
+}
+
+// invalid contributions per minute tolerated before a ban, defaults to 20
+fn ban_threshold() -> Result<u32, error::Error> {
+    match std::env::var("BAN_THRESHOLD") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(20),
+    }
+}
+
+// denominated in minutes, defaults to 10
+// doubles with every repeat offence
+fn ban_duration() -> Result<i64, error::Error> {
+    match std::env::var("BAN_DURATION") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(10),
+    }
+}
+
+// comma separated ip addresses of the proxies trusted to set the forwarding headers, defaults to none
+fn trusted_proxies() -> Result<Vec<std::net::IpAddr>, error::Error> {
+    match std::env::var("TRUSTED_PROXIES") {
+        Ok(string) => string
+            .split(',')
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty())
+            .map(|ip| {
+                ip.parse().map_err(|err| {
+                    error::Error::Internal(format!("invalid trusted proxy {}: {:?}", ip, err))
+                })
+            })
+            .collect(),
+        Err(_) => Ok(vec![]),
+    }
+}
+
+// the admin routes are only served if set
+fn admin_token() -> Option<String> {
+    std::env::var("ADMIN_TOKEN").ok()
+}
+
+// either "pubsub", "poll" or "webhook",
+// defaults to "webhook" for deployments that configured the helius webhook before it was optional,
+// and to "pubsub" otherwise
+fn event_source() -> Result<EventSource, error::Error> {
+    match std::env::var("EVENT_SOURCE") {
+        Ok(string) => string.parse(),
+        Err(_) if std::env::var("HELIUS_AUTH_TOKEN").is_ok() => {
+            log::warn!("EVENT_SOURCE unset, defaulting to webhook since HELIUS_AUTH_TOKEN is set");
+            Ok(EventSource::Webhook)
+        }
+        Err(_) => Ok(EventSource::Pubsub),
+    }
+}
+
+// defaults to the websocket endpoint of the rpc url
+fn ws_url() -> Result<String, error::Error> {
+    match std::env::var("WS_URL") {
+        Ok(string) => Ok(string),
+        Err(_) => {
+            let rpc_url = std::env::var("RPC_URL")?;
+            Ok(rpc_url
+                .replacen("https://", "wss://", 1)
+                .replacen("http://", "ws://", 1))
+        }
+    }
+}
+
+// denominated in minutes, defaults to 10
+fn backfill_interval() -> Result<u64, error::Error> {
+    match std::env::var("BACKFILL_INTERVAL") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(10),
+    }
+}
+
+// denominated in seconds, defaults to 5
+fn poll_interval() -> Result<u64, error::Error> {
+    match std::env::var("POLL_INTERVAL") {
+        Ok(string) => string.parse().map_err(From::from),
+        Err(_) => Ok(5),
+    }
+}
+
+fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// either "attribute" or "merkle", defaults to "attribute"
+fn balance_mode() -> Result<BalanceMode, error::Error> {
+    match std::env::var("BALANCE_MODE") {
+        Ok(string) => string.parse(),
+        Err(_) => Ok(BalanceMode::Attribute),
+    }
+}
+
 #[get("/health")]
 async fn health() -> impl Responder {
     HttpResponse::Ok().body("ok")
@@ -13,7 +13,13 @@ use solana_sdk::{
 };
 use steel::AccountDeserialize;
 
-use crate::{database, error::Error};
+use crate::{
+    balances::BalanceTree,
+    database,
+    error::Error,
+    publisher::{self, BlockPublisher},
+    tx,
+};
 
 pub const BUFFER_OPERATOR: u64 = 5;
 const MIN_DIFFICULTY: Option<u64> = Some(7);
@@ -22,6 +28,10 @@ pub struct Operator {
     /// The pool authority keypair.
     pub keypair: Keypair,
 
+    /// The authority the pool was launched with.
+    /// Seeds the pool address, and differs from the keypair once pool authority is transferred.
+    pub pool_authority: Pubkey,
+
     /// Solana RPC client.
     pub rpc_client: RpcClient,
 
@@ -31,41 +41,49 @@ pub struct Operator {
     /// Postgres connection pool.
     pub db_client: deadpool_postgres::Pool,
 
-    /// The operator commission in % percentage.
-    /// Applied to the miner and staker rewards.
-    pub operator_commission: u64,
+    /// Publishes the record of each submission (none if disabled).
+    pub block_publisher: Option<Box<dyn BlockPublisher>>,
 }
 
 impl Operator {
     pub fn new() -> Result<Operator, Error> {
         let keypair = Self::keypair()?;
+        let pool_authority = Self::pool_authority(&keypair)?;
         let rpc_client = Self::rpc_client()?;
         let jito_client = Self::jito_client();
         let db_client = database::create_pool();
-        let operator_commission = Self::operator_commission()?;
-        log::info!("operator commision: {}", operator_commission);
+        let block_publisher = publisher::from_env()?;
         Ok(Operator {
             keypair,
+            pool_authority,
             rpc_client,
             jito_client,
             db_client,
-            operator_commission,
+            block_publisher,
         })
     }
 
+    pub fn pool_address(&self) -> Pubkey {
+        let (pool_pda, _) = ore_pool_api::state::pool_pda(self.pool_authority);
+        pool_pda
+    }
+
+    pub fn proof_address(&self) -> Pubkey {
+        let (proof_pda, _) = ore_pool_api::state::pool_proof_pda(self.pool_address());
+        proof_pda
+    }
+
     pub async fn get_pool(&self) -> Result<Pool, Error> {
-        let authority = self.keypair.pubkey();
         let rpc_client = &self.rpc_client;
-        let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
+        let pool_pda = self.pool_address();
         let data = rpc_client.get_account_data(&pool_pda).await?;
         let pool = Pool::try_from_bytes(data.as_slice())?;
         Ok(*pool)
     }
 
     pub async fn get_member_onchain(&self, member_authority: &Pubkey) -> Result<Member, Error> {
-        let authority = self.keypair.pubkey();
         let rpc_client = &self.rpc_client;
-        let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
+        let pool_pda = self.pool_address();
         let (member_pda, _) = ore_pool_api::state::member_pda(*member_authority, pool_pda);
         let data = rpc_client.get_account_data(&member_pda).await?;
         let member = Member::try_from_bytes(data.as_slice())?;
@@ -78,17 +96,14 @@ impl Operator {
     ) -> Result<ore_pool_types::Member, Error> {
         let db_client = self.db_client.get().await?;
         let member_authority = Pubkey::from_str(member_authority)?;
-        let pool_authority = self.keypair.pubkey();
-        let (pool_pda, _) = ore_pool_api::state::pool_pda(pool_authority);
+        let pool_pda = self.pool_address();
         let (member_pda, _) = ore_pool_api::state::member_pda(member_authority, pool_pda);
         database::read_member(&db_client, &member_pda.to_string()).await
     }
 
     pub async fn get_proof(&self) -> Result<Proof, Error> {
-        let authority = self.keypair.pubkey();
         let rpc_client = &self.rpc_client;
-        let (pool_pda, _) = ore_pool_api::state::pool_pda(authority);
-        let (proof_pda, _) = ore_pool_api::state::pool_proof_pda(pool_pda);
+        let proof_pda = self.proof_address();
         let data = rpc_client.get_account_data(&proof_pda).await?;
         let proof = Proof::try_from_bytes(data.as_slice())?;
         Ok(*proof)
@@ -123,6 +138,34 @@ impl Operator {
         Ok(())
     }
 
+    /// builds the balance root from the db and posts it on-chain, if it changed.
+    /// returns the tree backing the posted root for serving member proofs.
+    pub async fn post_balance_root(&self) -> Result<BalanceTree, Error> {
+        let db_client = self.db_client.get().await?;
+        let balances = database::read_member_balances(&db_client, &self.pool_address()).await?;
+        let balance_tree = BalanceTree::new(balances);
+        let root = balance_tree.root();
+        let pool = self.get_pool().await?;
+        if root.ne(&pool.balance_root) {
+            let ix = ore_pool_api::sdk::update_balance_root(
+                self.keypair.pubkey(),
+                self.pool_address(),
+                root,
+            );
+            let sig = tx::submit::submit_and_confirm_instructions(
+                &self.keypair,
+                &self.rpc_client,
+                &self.jito_client,
+                &[ix],
+                50_000,
+                2_000,
+            )
+            .await?;
+            log::info!("balance root sig: {:?}", sig);
+        }
+        Ok(balance_tree)
+    }
+
     async fn get_config(&self) -> Result<Config, Error> {
         let config_pda = ore_api::consts::CONFIG_ADDRESS;
         let rpc_client = &self.rpc_client;
@@ -148,6 +191,14 @@ impl Operator {
         std::env::var("KEYPAIR_PATH").map_err(From::from)
     }
 
+    // defaults to the keypair if the pool authority was never transferred
+    fn pool_authority(keypair: &Keypair) -> Result<Pubkey, Error> {
+        match std::env::var("POOL_AUTHORITY") {
+            Ok(str) => Pubkey::from_str(str.as_str()).map_err(From::from),
+            Err(_) => Ok(keypair.pubkey()),
+        }
+    }
+
     fn rpc_client() -> Result<RpcClient, Error> {
         let rpc_url = Operator::rpc_url()?;
         Ok(RpcClient::new_with_commitment(
@@ -164,10 +215,4 @@ impl Operator {
     fn rpc_url() -> Result<String, Error> {
         std::env::var("RPC_URL").map_err(From::from)
     }
-
-    fn operator_commission() -> Result<u64, Error> {
-        let str = std::env::var("OPERATOR_COMMISSION")?;
-        let commission: u64 = str.parse()?;
-        Ok(commission)
-    }
 }
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+#[cfg(feature = "s3")]
+use aws_sdk_s3::{
+    config::{BehaviorVersion, Credentials, Region},
+    primitives::ByteStream,
+};
+use ore_pool_types::Block;
+
+use crate::error::Error;
+
+/// Publishes the record of each submission,
+/// so anyone can download it and recompute the on-chain attestation.
+#[async_trait::async_trait]
+pub trait BlockPublisher: Send + Sync {
+    /// Writes the block and returns where it was written to.
+    async fn publish(&self, block: &Block) -> Result<String, Error>;
+}
+
+/// Selects the block publisher from env vars.
+/// Returns none if publishing is disabled.
+pub fn from_env() -> Result<Option<Box<dyn BlockPublisher>>, Error> {
+    match std::env::var("BLOCK_PUBLISHER") {
+        Ok(str) => match str.as_str() {
+            "fs" => Ok(Some(Box::new(FsPublisher::from_env()))),
+            #[cfg(feature = "s3")]
+            "s3" => Ok(Some(Box::new(S3Publisher::from_env()?))),
+            #[cfg(not(feature = "s3"))]
+            "s3" => Err(Error::Internal(
+                "the s3 block publisher requires the s3 feature".to_string(),
+            )),
+            _ => Err(Error::Internal(format!("invalid block publisher: {}", str))),
+        },
+        Err(_) => Ok(None),
+    }
+}
+
+/// The object key of a block, relative to the publisher root.
+fn block_key(block: &Block) -> String {
+    format!("blocks/{}.json", block.challenge.lash_hash_at)
+}
+
+/// Writes blocks to a local directory.
+pub struct FsPublisher {
+    /// The root directory.
+    dir: PathBuf,
+}
+
+impl FsPublisher {
+    fn from_env() -> Self {
+        let dir = std::env::var("BLOCK_DIR").unwrap_or(".".to_string());
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockPublisher for FsPublisher {
+    async fn publish(&self, block: &Block) -> Result<String, Error> {
+        let path = self.dir.join(block_key(block));
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec(block)?;
+        tokio::fs::write(&path, json).await?;
+        Ok(path.display().to_string())
+    }
+}
+
+/// Writes blocks to an S3 compatible bucket.
+#[cfg(feature = "s3")]
+pub struct S3Publisher {
+    /// The S3 client.
+    client: aws_sdk_s3::Client,
+
+    /// The bucket name.
+    bucket: String,
+}
+
+#[cfg(feature = "s3")]
+impl S3Publisher {
+    fn from_env() -> Result<Self, Error> {
+        let bucket = std::env::var("S3_BUCKET")?;
+        let region = std::env::var("S3_REGION").unwrap_or("us-east-1".to_string());
+        let access_key_id = std::env::var("S3_ACCESS_KEY_ID")?;
+        let secret_access_key = std::env::var("S3_SECRET_ACCESS_KEY")?;
+        let credentials = Credentials::new(
+            access_key_id,
+            secret_access_key,
+            None,
+            None,
+            "ore-pool-server",
+        );
+        let mut config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(region))
+            .credentials_provider(credentials);
+        // other S3 compatible stores are configured with a custom endpoint
+        if let Ok(endpoint) = std::env::var("S3_ENDPOINT") {
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+        let client = aws_sdk_s3::Client::from_conf(config.build());
+        Ok(Self { client, bucket })
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait::async_trait]
+impl BlockPublisher for S3Publisher {
+    async fn publish(&self, block: &Block) -> Result<String, Error> {
+        let key = block_key(block);
+        let json = serde_json::to_vec(block)?;
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .content_type("application/json")
+            .body(ByteStream::from(json))
+            .send()
+            .await
+            .map_err(|err| Error::Internal(format!("s3 put object: {}", err)))?;
+        Ok(format!("s3://{}/{}", self.bucket, key))
+    }
+}
@@ -0,0 +1,545 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    contributions::{Contribution, Contributions, PoolMiningEvent},
+    error::Error,
+};
+
+/// Splits the rewards of a mining event between pool members.
+pub trait RewardPolicy: Send + Sync {
+    /// The name of the policy, as configured.
+    fn name(&self) -> &'static str;
+
+    /// Returns the rewards of each member,
+    /// given the rewards left for miners after the operator commission.
+    /// Leaves the policy as is, since the distribution may yet be rolled back.
+    fn distribute(
+        &self,
+        contributions: &mut Contributions,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+    ) -> Vec<(Pubkey, u64)>;
+
+    /// Returns the state of the policy once the distribution is recorded, if the policy keeps any.
+    /// Persisted with the distribution and only applied once it commits.
+    fn record(
+        &self,
+        _contributions: &Contributions,
+        _event: &PoolMiningEvent,
+        _miner_rewards: u64,
+        _rewards: &[(Pubkey, u64)],
+    ) -> Result<Option<Vec<u8>>, Error> {
+        Ok(None)
+    }
+
+    /// Replaces the state of the policy with a recorded one.
+    fn restore(&mut self, _state: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Selects the reward policy from env vars.
+/// Defaults to pplns over every challenge still in memory.
+pub fn from_env() -> Result<Box<dyn RewardPolicy>, Error> {
+    let policy = std::env::var("REWARD_POLICY").unwrap_or("pplns".to_string());
+    match policy.as_str() {
+        "proportional" => Ok(Box::new(Proportional)),
+        "pplns" => {
+            let shares = match std::env::var("PPLNS_SHARES") {
+                Ok(str) => Some(str.parse()?),
+                Err(_) => None,
+            };
+            Ok(Box::new(Pplns { shares }))
+        }
+        "pps" => {
+            let window = match std::env::var("PPS_WINDOW") {
+                Ok(str) => str.parse()?,
+                Err(_) => DEFAULT_PPS_WINDOW,
+            };
+            let price = match std::env::var("PPS_PRICE") {
+                Ok(str) => Some(str.parse()?),
+                Err(_) => None,
+            };
+            Ok(Box::new(Pps::new(window, price)))
+        }
+        _ => Err(Error::Internal(format!(
+            "invalid reward policy: {}",
+            policy
+        ))),
+    }
+}
+
+/// Splits rewards pro rata to the summed scores of each member.
+fn split(scores: HashMap<Pubkey, u64>, rewards: u64) -> Vec<(Pubkey, u64)> {
+    let total_score: u128 = scores.values().map(|score| *score as u128).sum();
+    scores
+        .into_iter()
+        .map(|(member, score)| {
+            let member_rewards = (rewards as u128)
+                .saturating_mul(score as u128)
+                .checked_div(total_score)
+                .unwrap_or(0) as u64;
+            (member, member_rewards)
+        })
+        .collect()
+}
+
+/// The contributions to the challenge that produced the mining event.
+fn round<'a>(
+    contributions: &'a Contributions,
+    event: &PoolMiningEvent,
+) -> impl Iterator<Item = &'a Contribution> {
+    contributions
+        .miners
+        .get(&(event.mine_event.last_hash_at as u64))
+        .into_iter()
+        .flat_map(|miner_contributions| miner_contributions.contributions.iter())
+}
+
+/// Proportional per round.
+/// Only the contributions to the challenge that produced the rewards are paid.
+pub struct Proportional;
+
+impl RewardPolicy for Proportional {
+    fn name(&self) -> &'static str {
+        "proportional"
+    }
+
+    fn distribute(
+        &self,
+        contributions: &mut Contributions,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+    ) -> Vec<(Pubkey, u64)> {
+        let scores = round(contributions, event)
+            .map(|contribution| (contribution.member, contribution.score))
+            .collect();
+        split(scores, miner_rewards)
+    }
+}
+
+/// Pay per last n shares.
+/// Rewards are split across the most recent contributions, walking back from the newest challenge.
+/// The window is bounded by the challenges kept in memory, which is also the window if n is unset.
+pub struct Pplns {
+    /// The number of shares to pay.
+    shares: Option<usize>,
+}
+
+impl RewardPolicy for Pplns {
+    fn name(&self) -> &'static str {
+        "pplns"
+    }
+
+    fn distribute(
+        &self,
+        contributions: &mut Contributions,
+        _event: &PoolMiningEvent,
+        miner_rewards: u64,
+    ) -> Vec<(Pubkey, u64)> {
+        let Some(shares) = self.shares else {
+            let (_, scores) = contributions.scores();
+            return split(HashMap::from_iter(scores), miner_rewards);
+        };
+        let mut rounds: Vec<_> = contributions.miners.iter().collect();
+        rounds.sort_by_key(|(last_hash_at, _)| std::cmp::Reverse(**last_hash_at));
+        let mut scores: HashMap<Pubkey, u64> = HashMap::new();
+        for contribution in rounds
+            .into_iter()
+            .flat_map(|(_, miner_contributions)| {
+                // best shares first, for a deterministic cut within a challenge
+                let mut round: Vec<_> = miner_contributions.contributions.iter().collect();
+                round.sort_by_key(|contribution| std::cmp::Reverse(contribution.score));
+                round
+            })
+            .take(shares)
+        {
+            *scores.entry(contribution.member).or_insert(0) += contribution.score;
+        }
+        split(scores, miner_rewards)
+    }
+}
+
+/// The number of mining events averaged over to price shares.
+const DEFAULT_PPS_WINDOW: usize = 60;
+
+/// The units of score a share price is quoted for,
+/// about the score of a difficulty 20 solution.
+const PPS_PRICE_SCALE: u128 = 1_000_000;
+
+/// Full pay per share.
+/// Each share is paid a fixed price per unit of score, regardless of the rewards of the event.
+/// The price is set with `PPS_PRICE`, or else priced at the average rewards per unit of score
+/// over recent mining events, boost rewards included.
+/// Payouts are funded from the rewards of the event and the reserve of rewards earned but not
+/// paid out. When they fall short, members are paid pro rata and the rest is owed to them,
+/// to be paid before new shares once the reserve recovers.
+pub struct Pps {
+    /// The recorded rounds, reserve and shortfall.
+    state: PpsState,
+
+    /// The number of rounds to average over.
+    window: usize,
+
+    /// The fixed price per `PPS_PRICE_SCALE` units of score, if set.
+    price: Option<u64>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct PpsState {
+    /// The miner rewards and total score of recent rounds, by last hash at.
+    history: BTreeMap<i64, (u64, u64)>,
+
+    /// The miner rewards earned but not yet paid out.
+    reserve: u64,
+
+    /// The rewards owed to members from rounds the reserve could not cover.
+    owed: BTreeMap<Pubkey, u64>,
+}
+
+impl Pps {
+    pub fn new(window: usize, price: Option<u64>) -> Self {
+        Self {
+            state: PpsState::default(),
+            window,
+            price,
+        }
+    }
+
+    /// The recent rounds with the round of this event,
+    /// keeping the latest rounds in the window whatever order events arrive in.
+    fn history(
+        &self,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+        total_score: u64,
+    ) -> BTreeMap<i64, (u64, u64)> {
+        let mut history = self.state.history.clone();
+        history.insert(event.mine_event.last_hash_at, (miner_rewards, total_score));
+        while history.len() > self.window {
+            history.pop_first();
+        }
+        history
+    }
+
+    /// The price per `PPS_PRICE_SCALE` units of score.
+    fn price(&self, history: &BTreeMap<i64, (u64, u64)>) -> u128 {
+        if let Some(price) = self.price {
+            return price as u128;
+        }
+        let (window_rewards, window_score) = history
+            .values()
+            .fold((0u128, 0u128), |(rewards, score), (r, s)| {
+                (rewards + *r as u128, score + *s as u128)
+            });
+        window_rewards
+            .saturating_mul(PPS_PRICE_SCALE)
+            .checked_div(window_score)
+            .unwrap_or(0)
+    }
+
+    /// The rewards due to each member for the shares of this event, and the rewards owed.
+    fn dues(
+        &self,
+        contributions: &Contributions,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+    ) -> (u64, HashMap<Pubkey, u128>) {
+        let mut scores: HashMap<Pubkey, u64> = HashMap::new();
+        for contribution in round(contributions, event) {
+            *scores.entry(contribution.member).or_insert(0) += contribution.score;
+        }
+        let total_score: u64 = scores.values().sum();
+        let price = self.price(&self.history(event, miner_rewards, total_score));
+        let mut dues: HashMap<Pubkey, u128> = scores
+            .into_iter()
+            .map(|(member, score)| {
+                let due = (score as u128).saturating_mul(price) / PPS_PRICE_SCALE;
+                (member, due)
+            })
+            .collect();
+        for (member, owed) in self.state.owed.iter() {
+            *dues.entry(*member).or_insert(0) += *owed as u128;
+        }
+        (total_score, dues)
+    }
+}
+
+impl RewardPolicy for Pps {
+    fn name(&self) -> &'static str {
+        "pps"
+    }
+
+    fn distribute(
+        &self,
+        contributions: &mut Contributions,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+    ) -> Vec<(Pubkey, u64)> {
+        let (_, dues) = self.dues(contributions, event, miner_rewards);
+
+        // pay out no more than the round earned and the reserve holds,
+        // pro rata to what is due when it falls short
+        let total_due: u128 = dues.values().sum();
+        let budget = (miner_rewards as u128) + (self.state.reserve as u128);
+        if total_due > budget {
+            log::warn!("pps payout short of reserve: {} {}", total_due, budget);
+        }
+        dues.into_iter()
+            .map(|(member, due)| {
+                let paid = if total_due > budget {
+                    due.saturating_mul(budget) / total_due
+                } else {
+                    due
+                };
+                (member, paid as u64)
+            })
+            .collect()
+    }
+
+    fn record(
+        &self,
+        contributions: &Contributions,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+        rewards: &[(Pubkey, u64)],
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let (total_score, dues) = self.dues(contributions, event, miner_rewards);
+        let paid: HashMap<Pubkey, u64> = rewards.iter().copied().collect();
+        let owed = dues
+            .into_iter()
+            .filter_map(|(member, due)| {
+                let paid = paid.get(&member).copied().unwrap_or(0) as u128;
+                let owed = due.saturating_sub(paid).min(u64::MAX as u128) as u64;
+                (owed > 0).then_some((member, owed))
+            })
+            .collect();
+        let total_paid = paid
+            .values()
+            .fold(0u64, |total, paid| total.saturating_add(*paid));
+        let state = PpsState {
+            history: self.history(event, miner_rewards, total_score),
+            reserve: self
+                .state
+                .reserve
+                .saturating_add(miner_rewards)
+                .saturating_sub(total_paid),
+            owed,
+        };
+        Ok(Some(bincode::serialize(&state)?))
+    }
+
+    fn restore(&mut self, state: &[u8]) -> Result<(), Error> {
+        self.state = bincode::deserialize(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use drillx::Solution;
+    use ore_api::event::MineEvent;
+    use solana_sdk::signature::Signature;
+
+    use super::*;
+
+    fn contributions(rounds: &[(u64, &[(Pubkey, u64)])]) -> Contributions {
+        let mut contributions = Contributions::new(u8::MAX);
+        for (last_hash_at, scores) in rounds {
+            contributions.insert(*last_hash_at);
+            let miner_contributions = contributions.miners.get_mut(last_hash_at).unwrap();
+            for (member, score) in scores.iter() {
+                miner_contributions.contributions.insert(Contribution {
+                    member: *member,
+                    score: *score,
+                    solution: Solution::new([0; 16], [0; 8]),
+                });
+                miner_contributions.total_score += score;
+            }
+        }
+        contributions
+    }
+
+    fn event(last_hash_at: i64) -> PoolMiningEvent {
+        PoolMiningEvent {
+            signature: Signature::default(),
+            block: 0,
+            timestamp: 0,
+            mine_event: MineEvent {
+                balance: 0,
+                difficulty: 0,
+                last_hash_at,
+                timing: 0,
+                net_reward: 0,
+                net_base_reward: 0,
+                net_miner_boost_reward: 0,
+                net_staker_boost_reward: 0,
+            },
+            member_rewards: HashMap::new(),
+            member_scores: HashMap::new(),
+        }
+    }
+
+    fn sorted(rewards: Vec<(Pubkey, u64)>) -> Vec<(Pubkey, u64)> {
+        let mut rewards = rewards;
+        rewards.sort();
+        rewards
+    }
+
+    /// distributes and records the rewards of an event, as the aggregator does
+    fn settle(
+        pps: &mut Pps,
+        contributions: &mut Contributions,
+        event: &PoolMiningEvent,
+        miner_rewards: u64,
+    ) -> Vec<(Pubkey, u64)> {
+        let rewards = pps.distribute(contributions, event, miner_rewards);
+        let state = pps
+            .record(contributions, event, miner_rewards, &rewards)
+            .unwrap()
+            .unwrap();
+        pps.restore(&state).unwrap();
+        sorted(rewards)
+    }
+
+    #[test]
+    fn split_leaves_rounding_dust_unpaid() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let rewards = split(HashMap::from([(a, 1), (b, 1), (c, 2)]), 10);
+        assert_eq!(rewards.iter().map(|(_, r)| r).sum::<u64>(), 9);
+        assert_eq!(sorted(rewards), sorted(vec![(a, 2), (b, 2), (c, 5)]));
+
+        // nothing to split without scores
+        assert_eq!(split(HashMap::from([(a, 0)]), 10), vec![(a, 0)]);
+
+        // no overflow with large rewards and scores
+        let rewards = split(HashMap::from([(a, u64::MAX), (b, u64::MAX)]), u64::MAX);
+        assert_eq!(
+            sorted(rewards),
+            sorted(vec![(a, u64::MAX / 2), (b, u64::MAX / 2)])
+        );
+    }
+
+    #[test]
+    fn proportional_pays_the_round_of_the_event() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut contributions = contributions(&[(1, &[(a, 3), (b, 1)]), (2, &[(a, 1)])]);
+        let rewards = Proportional.distribute(&mut contributions, &event(1), 100);
+        assert_eq!(sorted(rewards), sorted(vec![(a, 75), (b, 25)]));
+        let rewards = Proportional.distribute(&mut contributions, &event(2), 100);
+        assert_eq!(rewards, vec![(a, 100)]);
+        assert!(Proportional
+            .distribute(&mut contributions, &event(3), 100)
+            .is_empty());
+    }
+
+    #[test]
+    fn pplns_pays_the_last_n_shares() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut contributions = contributions(&[(1, &[(a, 4)]), (2, &[(b, 1), (c, 3)])]);
+
+        // every share in memory
+        let pplns = Pplns { shares: None };
+        let rewards = pplns.distribute(&mut contributions, &event(2), 80);
+        assert_eq!(sorted(rewards), sorted(vec![(a, 40), (b, 10), (c, 30)]));
+
+        // newest challenge first, best shares first within it
+        let pplns = Pplns { shares: Some(1) };
+        let rewards = pplns.distribute(&mut contributions, &event(2), 80);
+        assert_eq!(rewards, vec![(c, 80)]);
+        let pplns = Pplns { shares: Some(2) };
+        let rewards = pplns.distribute(&mut contributions, &event(2), 80);
+        assert_eq!(sorted(rewards), sorted(vec![(b, 20), (c, 60)]));
+    }
+
+    #[test]
+    fn pps_pays_a_fixed_price_from_the_reserve() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut contributions = contributions(&[(1, &[(a, 100), (b, 50)]), (2, &[(a, 500)])]);
+
+        // a gram per unit of score, whatever the event earned
+        let mut pps = Pps::new(DEFAULT_PPS_WINDOW, Some(PPS_PRICE_SCALE as u64));
+        let rewards = settle(&mut pps, &mut contributions, &event(1), 1_000);
+        assert_eq!(rewards, sorted(vec![(a, 100), (b, 50)]));
+        assert_eq!(pps.state.reserve, 850);
+
+        // covered by the reserve
+        let rewards = settle(&mut pps, &mut contributions, &event(2), 0);
+        assert_eq!(rewards, vec![(a, 500)]);
+        assert_eq!(pps.state.reserve, 350);
+        assert!(pps.state.owed.is_empty());
+    }
+
+    #[test]
+    fn pps_owes_the_shortfall() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut contributions = contributions(&[(1, &[(a, 300), (b, 100)]), (2, &[])]);
+        let mut pps = Pps::new(DEFAULT_PPS_WINDOW, Some(PPS_PRICE_SCALE as u64));
+
+        // paid pro rata, the rest is owed
+        let rewards = settle(&mut pps, &mut contributions, &event(1), 100);
+        assert_eq!(rewards, sorted(vec![(a, 75), (b, 25)]));
+        assert_eq!(pps.state.reserve, 0);
+        assert_eq!(pps.state.owed, BTreeMap::from([(a, 225), (b, 75)]));
+
+        // repaid once the reserve recovers, without new shares
+        let rewards = settle(&mut pps, &mut contributions, &event(2), 1_000);
+        assert_eq!(rewards, sorted(vec![(a, 225), (b, 75)]));
+        assert_eq!(pps.state.reserve, 700);
+        assert!(pps.state.owed.is_empty());
+    }
+
+    #[test]
+    fn pps_prices_shares_over_the_window() {
+        let a = Pubkey::new_unique();
+        let mut contributions =
+            contributions(&[(1, &[(a, 100)]), (2, &[(a, 100)]), (3, &[(a, 100)])]);
+        let mut pps = Pps::new(2, None);
+
+        // priced at the rewards of the event alone
+        let rewards = settle(&mut pps, &mut contributions, &event(1), 100);
+        assert_eq!(rewards, vec![(a, 100)]);
+
+        // averaged with the previous event
+        let rewards = settle(&mut pps, &mut contributions, &event(2), 300);
+        assert_eq!(rewards, vec![(a, 200)]);
+        assert_eq!(pps.state.reserve, 100);
+
+        // the first event fell out of the window, the reserve covers 100 of the 150 due
+        let rewards = settle(&mut pps, &mut contributions, &event(3), 0);
+        assert_eq!(rewards, vec![(a, 100)]);
+        assert_eq!(pps.state.reserve, 0);
+        assert_eq!(pps.state.owed, BTreeMap::from([(a, 50)]));
+        assert_eq!(pps.state.history.len(), 2);
+    }
+
+    #[test]
+    fn pps_state_survives_a_restart() {
+        let a = Pubkey::new_unique();
+        let mut contributions = contributions(&[(1, &[(a, 400)])]);
+        let pps = Pps::new(DEFAULT_PPS_WINDOW, Some(PPS_PRICE_SCALE as u64));
+        let rewards = pps.distribute(&mut contributions, &event(1), 100);
+        let state = pps
+            .record(&contributions, &event(1), 100, &rewards)
+            .unwrap()
+            .unwrap();
+
+        // distributing leaves the state as is until it is restored
+        assert!(pps.state.owed.is_empty());
+        let mut restarted = Pps::new(DEFAULT_PPS_WINDOW, Some(PPS_PRICE_SCALE as u64));
+        restarted.restore(&state).unwrap();
+        assert_eq!(restarted.state.owed, BTreeMap::from([(a, 300)]));
+        assert_eq!(restarted.state.history, BTreeMap::from([(1, (100, 400))]));
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use arc_swap::ArcSwapOption;
+use dashmap::{mapref::entry::Entry, DashMap};
+use ore_pool_types::Challenge;
+
+use crate::contributions::{Contribution, Miner, MinerContributions, Winner};
+
+/// The contributions to the current challenge.
+/// Members insert concurrently, without the aggregator lock,
+/// until the round is sealed for submission.
+pub struct Round {
+    /// The challenge the contributions solve.
+    pub challenge: Challenge,
+
+    /// The challenge submitted before this one, to tell stale contributions apart.
+    pub previous_challenge: Option<Challenge>,
+
+    /// The number of member ids to partition the nonce space by.
+    pub num_members: u64,
+
+    /// The best contribution of each member, sharded by member.
+    contributions: DashMap<Miner, Contribution>,
+
+    /// The best solution across members.
+    winner: ArcSwapOption<Winner>,
+
+    /// The sum of the contribution scores.
+    total_score: AtomicU64,
+
+    /// Whether new contributions are turned away.
+    sealed: AtomicBool,
+
+    /// The number of inserts in flight, waited out by the seal.
+    inserting: AtomicUsize,
+
+    /// Wakes the seal once the last insert in flight is done.
+    drained: tokio::sync::Notify,
+
+    /// Wakes the aggregator on the first contribution.
+    first_contribution: tokio::sync::Notify,
+}
+
+/// The outcome of inserting a contribution into the round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Insert {
+    /// The first contribution of the member.
+    New,
+
+    /// The contribution improved the score of the member.
+    Updated,
+
+    /// The member already contributed an equal or better score.
+    Duplicate,
+
+    /// The round was sealed for submission.
+    Sealed,
+}
+
+impl Round {
+    /// opens a round for the challenge,
+    /// seeded with the contributions already counted for it, if any
+    pub fn new(
+        challenge: Challenge,
+        previous_challenge: Option<Challenge>,
+        num_members: u64,
+        seed: Option<&MinerContributions>,
+    ) -> Self {
+        let round = Self {
+            challenge,
+            previous_challenge,
+            num_members,
+            contributions: DashMap::new(),
+            winner: ArcSwapOption::empty(),
+            total_score: AtomicU64::new(0),
+            sealed: AtomicBool::new(false),
+            inserting: AtomicUsize::new(0),
+            drained: tokio::sync::Notify::new(),
+            first_contribution: tokio::sync::Notify::new(),
+        };
+        if let Some(seed) = seed {
+            for contribution in seed.contributions.iter() {
+                round
+                    .contributions
+                    .insert(contribution.member, *contribution);
+            }
+            round.total_score.store(seed.total_score, Ordering::SeqCst);
+            round.winner.store(seed.winner.map(Arc::new));
+        }
+        round
+    }
+
+    /// inserts the contribution if it improves the score of the member
+    pub fn insert(&self, contribution: Contribution, difficulty: u32) -> Insert {
+        // register the insert before checking the seal,
+        // so the seal either turns it away or waits for it
+        self.inserting.fetch_add(1, Ordering::SeqCst);
+        if self.sealed.load(Ordering::SeqCst) {
+            self.done_inserting();
+            return Insert::Sealed;
+        }
+
+        // only the shard of the member is locked
+        let insert = match self.contributions.entry(contribution.member) {
+            Entry::Occupied(mut entry) => {
+                let prev_score = entry.get().score;
+                if contribution.score > prev_score {
+                    entry.insert(contribution);
+                    self.total_score
+                        .fetch_add(contribution.score - prev_score, Ordering::SeqCst);
+                    log::info!(
+                        "updated contribution: {:?} {}",
+                        contribution.member,
+                        difficulty
+                    );
+                    Insert::Updated
+                } else {
+                    Insert::Duplicate
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(contribution);
+                self.total_score
+                    .fetch_add(contribution.score, Ordering::SeqCst);
+                log::info!("new contribution: {:?} {}", contribution.member, difficulty);
+                Insert::New
+            }
+        };
+        if insert != Insert::Duplicate {
+            self.update_winner(contribution, difficulty);
+            self.first_contribution.notify_one();
+        }
+        self.done_inserting();
+        insert
+    }
+
+    /// deregisters an insert, waking the seal if it was the last in flight
+    fn done_inserting(&self) {
+        if self.inserting.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.drained.notify_waiters();
+        }
+    }
+
+    /// the score of the best contribution of the member, if any
+    pub fn score(&self, member: &Miner) -> Option<u64> {
+        self.contributions
+            .get(member)
+            .map(|contribution| contribution.score)
+    }
+
+    pub fn total_score(&self) -> u64 {
+        self.total_score.load(Ordering::SeqCst)
+    }
+
+    /// waits until the round holds at least one contribution
+    pub async fn first_contribution(&self) {
+        if self.total_score() == 0 {
+            self.first_contribution.notified().await;
+        }
+    }
+
+    /// turns away new contributions and waits out the inserts in flight,
+    /// after which the snapshot is final
+    pub async fn seal(&self) {
+        self.sealed.store(true, Ordering::SeqCst);
+        loop {
+            // listen before checking, so the last insert cannot slip in between
+            let drained = self.drained.notified();
+            tokio::pin!(drained);
+            drained.as_mut().enable();
+            if self.inserting.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            drained.await;
+        }
+    }
+
+    /// accepts contributions again, e.g. after a failed submission
+    pub fn unseal(&self) {
+        self.sealed.store(false, Ordering::SeqCst);
+    }
+
+    /// copies the contributions out of the round.
+    /// consistent once sealed, a best effort view otherwise.
+    pub fn snapshot(&self) -> MinerContributions {
+        let contributions: HashSet<Contribution> = self
+            .contributions
+            .iter()
+            .map(|entry| *entry.value())
+            .collect();
+        let total_score = contributions
+            .iter()
+            .map(|contribution| contribution.score)
+            .sum();
+        MinerContributions {
+            contributions,
+            winner: self.winner.load_full().map(|winner| *winner),
+            total_score,
+        }
+    }
+
+    /// swaps in the contender if it beats the current winner
+    fn update_winner(&self, contribution: Contribution, difficulty: u32) {
+        self.winner.rcu(|winner| {
+            let winner: &Option<Arc<Winner>> = winner;
+            match winner {
+                Some(winner) if winner.difficulty >= difficulty => Some(winner.clone()),
+                _ => Some(Arc::new(Winner {
+                    solution: contribution.solution,
+                    difficulty,
+                })),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use drillx::Solution;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    fn round() -> Round {
+        let challenge = Challenge {
+            challenge: [0; 32],
+            lash_hash_at: 1,
+            min_difficulty: 8,
+            cutoff_time: 60,
+        };
+        Round::new(challenge, None, 2, None)
+    }
+
+    fn contribution(member: Pubkey, difficulty: u32) -> Contribution {
+        Contribution {
+            member,
+            score: 2u64.pow(difficulty),
+            solution: Solution::new([difficulty as u8; 16], [0; 8]),
+        }
+    }
+
+    #[test]
+    fn insert_keeps_the_best_contribution_of_each_member() {
+        let round = round();
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert_eq!(round.insert(contribution(a, 10), 10), Insert::New);
+        assert_eq!(round.insert(contribution(a, 9), 9), Insert::Duplicate);
+        assert_eq!(round.insert(contribution(a, 10), 10), Insert::Duplicate);
+        assert_eq!(round.insert(contribution(b, 11), 11), Insert::New);
+        assert_eq!(round.insert(contribution(a, 12), 12), Insert::Updated);
+        assert_eq!(round.score(&a), Some(4096));
+        assert_eq!(round.score(&b), Some(2048));
+        assert_eq!(round.total_score(), 4096 + 2048);
+
+        let snapshot = round.snapshot();
+        assert_eq!(snapshot.contributions.len(), 2);
+        assert_eq!(snapshot.total_score, round.total_score());
+        let winner = snapshot.winner.unwrap();
+        assert_eq!(winner.difficulty, 12);
+        assert_eq!(winner.solution.d, [12; 16]);
+    }
+
+    #[test]
+    fn new_is_seeded_with_counted_contributions() {
+        let seeded = round();
+        let a = Pubkey::new_unique();
+        seeded.insert(contribution(a, 10), 10);
+        let seed = seeded.snapshot();
+        let round = Round::new(seeded.challenge, None, 2, Some(&seed));
+        assert_eq!(round.score(&a), Some(1024));
+        assert_eq!(round.total_score(), 1024);
+        assert_eq!(round.snapshot().winner.unwrap().difficulty, 10);
+        assert_eq!(round.insert(contribution(a, 10), 10), Insert::Duplicate);
+    }
+
+    #[actix_web::test]
+    async fn seal_turns_away_contributions_until_unsealed() {
+        let round = round();
+        let a = Pubkey::new_unique();
+        round.seal().await;
+        assert_eq!(round.insert(contribution(a, 10), 10), Insert::Sealed);
+        assert_eq!(round.total_score(), 0);
+        assert!(round.snapshot().contributions.is_empty());
+
+        round.unseal();
+        assert_eq!(round.insert(contribution(a, 10), 10), Insert::New);
+    }
+
+    #[actix_web::test]
+    async fn seal_waits_out_inserts_in_flight() {
+        let round = Arc::new(round());
+
+        // an insert registered before the seal
+        round.inserting.fetch_add(1, Ordering::SeqCst);
+        let seal = tokio::spawn({
+            let round = round.clone();
+            async move { round.seal().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!seal.is_finished());
+
+        // inserts after the seal are turned away without holding it up
+        let a = Pubkey::new_unique();
+        assert_eq!(round.insert(contribution(a, 10), 10), Insert::Sealed);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!seal.is_finished());
+
+        // woken once the insert in flight is done
+        round.done_inserting();
+        tokio::time::timeout(Duration::from_secs(1), seal)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
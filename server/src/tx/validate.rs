@@ -161,8 +161,7 @@ pub fn validate_attribution(
     }
 
     // Validate any remaining instructions belong to lighthouse program or are Jito tips
-    for i in ore_pool_end_idx..n {
-        let ix = &instructions[i];
+    for (i, ix) in instructions.iter().enumerate().take(n).skip(ore_pool_end_idx) {
         let program_id_index = ix.program_id_index as usize;
         let program_id = transaction
             .message
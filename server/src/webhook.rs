@@ -1,123 +1,243 @@
 use std::{collections::HashMap, str::FromStr};
 
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
-use base64::{prelude::BASE64_STANDARD, Engine};
-use cached::proc_macro::cached;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use solana_sdk::signature::Signature;
 
-use crate::{contributions::PoolMiningEvent, error::Error};
+use crate::{contributions::PoolMiningEvent, error::Error, listener, operator::Operator};
 
-#[derive(serde::Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct RawPayload {
-    pub block_time: u64,
-    pub slot: u64,
-    pub meta: Meta,
-    pub transaction: Transaction,
+/// Authenticates webhook deliveries and parses them into transactions,
+/// so the pool can run against any rpc provider that pushes transactions.
+pub trait WebhookProvider: Send + Sync {
+    /// The name recorded as the source of quarantined events.
+    fn name(&self) -> &'static str;
+
+    /// Validates the request against the raw body.
+    fn authenticate(&self, req: &HttpRequest, body: &[u8]) -> Result<(), Error>;
+
+    /// Parses the raw body into its transactions.
+    /// Entries are parsed on their own, so that a malformed entry does not lose the rest.
+    fn parse(&self, body: &[u8]) -> Result<Vec<WebhookTransaction>, Error>;
 }
 
-#[derive(serde::Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct Meta {
-    pub log_messages: Vec<String>,
+/// A transaction in a webhook delivery.
+pub enum WebhookTransaction {
+    /// The transaction as delivered, with the mine event parsed from its logs.
+    /// The event is cross-checked against the rpc before it is submitted.
+    Event(PoolMiningEvent),
+
+    /// Only the signature was delivered, so the event is fetched from the rpc.
+    Signature(Signature),
+
+    /// The transaction did not carry a mine event.
+    Ignored(Signature),
+
+    /// The entry could not be parsed.
+    Malformed(Option<String>),
 }
 
-#[derive(serde::Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct Transaction {
-    pub signatures: Vec<String>,
+/// Selects the webhook provider from env vars.
+/// Defaults to helius.
+pub fn from_env() -> Result<Box<dyn WebhookProvider>, Error> {
+    let provider = std::env::var("WEBHOOK_PROVIDER").unwrap_or("helius".to_string());
+    match provider.as_str() {
+        "helius" => Ok(Box::new(Helius {
+            auth_token: std::env::var("HELIUS_AUTH_TOKEN")?,
+        })),
+        "hmac" => Ok(Box::new(HmacSigned {
+            secret: std::env::var("WEBHOOK_SECRET")?.into_bytes(),
+            header: std::env::var("WEBHOOK_SIGNATURE_HEADER").unwrap_or("X-Signature".to_string()),
+        })),
+        "signatures" => Ok(Box::new(SignatureList {
+            auth_token: std::env::var("WEBHOOK_AUTH_TOKEN")?,
+        })),
+        _ => Err(Error::Internal(format!(
+            "invalid webhook provider: {}",
+            provider
+        ))),
+    }
+}
+
+/// The outcome of a single transaction in a webhook batch.
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadStatus {
+    /// The mine event was sent to the aggregator.
+    Submitted,
+    /// The transaction did not carry a mine event.
+    Ignored,
+    /// The mine event failed verification.
+    Quarantined,
+    /// The payload could not be parsed.
+    Malformed,
+    /// The payload could not be processed right now, and the batch should be retried.
+    Failed,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct PayloadResult {
+    pub signature: Option<String>,
+    pub status: PayloadStatus,
 }
 
 pub async fn mine_event(
     tx: web::Data<tokio::sync::mpsc::Sender<PoolMiningEvent>>,
+    operator: web::Data<Operator>,
+    provider: web::Data<Box<dyn WebhookProvider>>,
     req: HttpRequest,
     bytes: web::Bytes,
 ) -> impl Responder {
-    // Validate auth header
-    if let Err(err) = auth(&req) {
+    // Validate request
+    if let Err(err) = provider.authenticate(&req, bytes.as_ref()) {
         log::error!("{:?}", err);
         return HttpResponse::Unauthorized().finish();
     }
 
-    // Parse payload
-    let bytes = bytes.to_vec();
-    let json = match serde_json::from_slice::<serde_json::Value>(bytes.as_slice()) {
-        Ok(json) => json,
+    // Parse batch
+    let transactions = match provider.parse(bytes.as_ref()) {
+        Ok(transactions) => transactions,
         Err(err) => {
             log::error!("{:?}", err);
             return HttpResponse::BadRequest().finish();
         }
     };
-    let payload = match serde_json::from_value::<Vec<RawPayload>>(json) {
-        Ok(payload) => payload,
-        Err(err) => {
-            log::error!("{:?}", err);
-            return HttpResponse::BadRequest().finish();
-        }
-    };
-    let payload = payload.first().unwrap();
 
-    // Parse mine event from transaction logs
-    let mine_event = match parse_mine_event(&payload) {
-        Ok(event) => event,
-        Err(err) => {
-            log::error!("{:?}", err);
-            return HttpResponse::BadRequest().finish();
+    // Process each transaction independently
+    let mut results = Vec::with_capacity(transactions.len());
+    for transaction in transactions {
+        let result = process_transaction(&tx, &operator, provider.name(), transaction).await;
+        results.push(result);
+    }
+
+    batch_response(&results)
+}
+
+/// Asks the provider to retry the batch if anything failed,
+/// events already submitted are skipped by the aggregator.
+fn batch_response(results: &[PayloadResult]) -> HttpResponse {
+    if results
+        .iter()
+        .any(|result| matches!(result.status, PayloadStatus::Failed))
+    {
+        return HttpResponse::InternalServerError().json(results);
+    }
+    HttpResponse::Ok().json(results)
+}
+
+async fn process_transaction(
+    tx: &tokio::sync::mpsc::Sender<PoolMiningEvent>,
+    operator: &Operator,
+    source: &str,
+    transaction: WebhookTransaction,
+) -> PayloadResult {
+    let result = |signature: &Signature, status| PayloadResult {
+        signature: Some(signature.to_string()),
+        status,
+    };
+    let event = match transaction {
+        WebhookTransaction::Event(event) => {
+            // Cross-check the payload against the rpc,
+            // since anything that passes authentication can post to the webhook
+            let signature = event.signature;
+            let reason = match listener::fetch_mine_event(operator, &signature, source).await {
+                Ok(Some((_, _, verified))) => {
+                    if bytemuck::bytes_of(&verified).ne(bytemuck::bytes_of(&event.mine_event)) {
+                        Some("mine event does not match rpc")
+                    } else {
+                        None
+                    }
+                }
+                Ok(None) => Some("rpc transaction has no mine event for this pool"),
+                Err(err) => {
+                    // let the provider retry once the rpc has the transaction
+                    log::error!("{:?}", err);
+                    return result(&signature, PayloadStatus::Failed);
+                }
+            };
+            if let Some(reason) = reason {
+                if let Err(err) =
+                    listener::quarantine(operator, &signature, source, reason, &event.mine_event)
+                        .await
+                {
+                    log::error!("{:?}", err);
+                    return result(&signature, PayloadStatus::Failed);
+                }
+                return result(&signature, PayloadStatus::Quarantined);
+            }
+            event
+        }
+        WebhookTransaction::Signature(signature) => {
+            match listener::fetch_mine_event(operator, &signature, source).await {
+                Ok(Some((slot, timestamp, mine_event))) => {
+                    listener::pool_mining_event(signature, slot, timestamp, mine_event)
+                }
+                Ok(None) => return result(&signature, PayloadStatus::Ignored),
+                Err(err) => {
+                    log::error!("{:?}", err);
+                    return result(&signature, PayloadStatus::Failed);
+                }
+            }
+        }
+        WebhookTransaction::Ignored(signature) => {
+            return result(&signature, PayloadStatus::Ignored);
+        }
+        WebhookTransaction::Malformed(signature) => {
+            return PayloadResult {
+                signature,
+                status: PayloadStatus::Malformed,
+            };
         }
     };
 
     // Submit mine event to aggregator
-    let event = PoolMiningEvent {
-        signature: Signature::from_str(payload.transaction.signatures.first().unwrap()).unwrap(),
-        block: payload.slot,
-        timestamp: payload.block_time,
-        mine_event: mine_event.clone(),
-        member_rewards: HashMap::new(),
-        member_scores: HashMap::new(),
-    };
+    let signature = event.signature;
     if let Err(err) = tx.send(event).await {
         log::error!("{:?}", err);
-        return HttpResponse::InternalServerError().finish();
+        return result(&signature, PayloadStatus::Failed);
     }
-
-    // Return success
-    HttpResponse::Ok().finish()
+    result(&signature, PayloadStatus::Submitted)
 }
 
+/// Parses each entry of a json array on its own.
+fn parse_entries<T: serde::de::DeserializeOwned>(
+    body: &[u8],
+) -> Result<Vec<Result<T, serde_json::Error>>, Error> {
+    let entries = serde_json::from_slice::<Vec<serde_json::Value>>(body)?;
+    Ok(entries.into_iter().map(serde_json::from_value).collect())
+}
 
-/// Parse a MineEvent from a Helius webhook event
-fn parse_mine_event(
-    payload: &RawPayload,
-) -> Result<ore_api::event::MineEvent, Error> {
-    // Find return data string
-    let log_messages = payload.meta.log_messages.as_slice();
-    let prefix = format!("Program return: {} ", ore_pool_api::ID.to_string());
-    let mut mine_event_str = "";
-    for log_message in log_messages.iter().rev() {
-        if log_message.starts_with(&prefix) {
-            mine_event_str = log_message.trim_start_matches(&prefix);
-            break;
-        }
-    }
-    if mine_event_str.is_empty() {
-        return Err(Error::Internal("webhook event missing return data".to_string()));
+/// Builds the transaction of a delivered entry, from its signature and logs.
+fn logged_transaction(
+    signature: &str,
+    slot: u64,
+    block_time: u64,
+    log_messages: &[String],
+) -> WebhookTransaction {
+    let Ok(signature) = Signature::from_str(signature) else {
+        log::error!("malformed webhook signature: {}", signature);
+        return WebhookTransaction::Malformed(Some(signature.to_string()));
+    };
+    match listener::parse_mine_event(log_messages) {
+        Ok(mine_event) => WebhookTransaction::Event(PoolMiningEvent {
+            signature,
+            block: slot,
+            timestamp: block_time,
+            mine_event,
+            member_rewards: HashMap::new(),
+            member_scores: HashMap::new(),
+        }),
+        Err(_) => WebhookTransaction::Ignored(signature),
     }
-
-    // Parse return data 
-    let mine_event = BASE64_STANDARD.decode(mine_event_str)?;
-    let mine_event: &ore_api::event::MineEvent =
-        bytemuck::try_from_bytes(mine_event.as_slice())
-            .map_err(|e| Error::Internal(e.to_string()))?;
-    Ok(*mine_event)
 }
 
-/// Validate the auth header
-fn auth(req: &HttpRequest) -> Result<(), Error> {
+/// Compares the authorization header against a static token.
+fn authenticate_token(req: &HttpRequest, auth_token: &str) -> Result<(), Error> {
     let header = req.headers().get("Authorization").ok_or(Error::Internal(
         "missing auth header in webhook event".to_string(),
     ))?;
     let header = header.to_str()?;
-    if header.to_string().ne(&helius_auth_token()) {
+    if header.ne(auth_token) {
         return Err(Error::Internal(
             "invalid auth header in webhook event".to_string(),
         ));
@@ -125,7 +245,319 @@ fn auth(req: &HttpRequest) -> Result<(), Error> {
     Ok(())
 }
 
-#[cached]
-fn helius_auth_token() -> String {
-    std::env::var("HELIUS_AUTH_TOKEN").expect("No helius auth token found")
+/// Helius raw webhooks,
+/// authenticated with the token helius includes in the authorization header.
+pub struct Helius {
+    auth_token: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RawPayload {
+    pub block_time: u64,
+    pub slot: u64,
+    pub meta: Meta,
+    pub transaction: Transaction,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    pub log_messages: Vec<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub signatures: Vec<String>,
+}
+
+impl WebhookProvider for Helius {
+    fn name(&self) -> &'static str {
+        "helius"
+    }
+
+    fn authenticate(&self, req: &HttpRequest, _body: &[u8]) -> Result<(), Error> {
+        authenticate_token(req, self.auth_token.as_str())
+    }
+
+    fn parse(&self, body: &[u8]) -> Result<Vec<WebhookTransaction>, Error> {
+        let transactions = parse_entries::<RawPayload>(body)?
+            .into_iter()
+            .map(|payload| match payload {
+                Ok(payload) => match payload.transaction.signatures.first() {
+                    Some(signature) => logged_transaction(
+                        signature,
+                        payload.slot,
+                        payload.block_time,
+                        payload.meta.log_messages.as_slice(),
+                    ),
+                    None => {
+                        log::error!("webhook payload missing signature");
+                        WebhookTransaction::Malformed(None)
+                    }
+                },
+                Err(err) => {
+                    log::error!("malformed webhook payload: {:?}", err);
+                    WebhookTransaction::Malformed(None)
+                }
+            })
+            .collect();
+        Ok(transactions)
+    }
+}
+
+/// A generic json format, signed with HMAC-SHA256 over the raw body.
+/// The hex encoded signature is sent in the signature header.
+pub struct HmacSigned {
+    secret: Vec<u8>,
+    header: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedPayload {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: u64,
+    pub log_messages: Vec<String>,
+}
+
+impl WebhookProvider for HmacSigned {
+    fn name(&self) -> &'static str {
+        "hmac"
+    }
+
+    fn authenticate(&self, req: &HttpRequest, body: &[u8]) -> Result<(), Error> {
+        let header = req
+            .headers()
+            .get(self.header.as_str())
+            .ok_or(Error::Internal(
+                "missing signature header in webhook event".to_string(),
+            ))?;
+        let signature = hex::decode(header.to_str()?.trim_start_matches("sha256="))
+            .map_err(|err| Error::Internal(err.to_string()))?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_slice())
+            .map_err(|err| Error::Internal(err.to_string()))?;
+        mac.update(body);
+        // constant time comparison
+        mac.verify_slice(signature.as_slice())
+            .map_err(|_| Error::Internal("invalid signature header in webhook event".to_string()))
+    }
+
+    fn parse(&self, body: &[u8]) -> Result<Vec<WebhookTransaction>, Error> {
+        let transactions = parse_entries::<SignedPayload>(body)?
+            .into_iter()
+            .map(|payload| match payload {
+                Ok(payload) => logged_transaction(
+                    payload.signature.as_str(),
+                    payload.slot,
+                    payload.block_time,
+                    payload.log_messages.as_slice(),
+                ),
+                Err(err) => {
+                    log::error!("malformed webhook payload: {:?}", err);
+                    WebhookTransaction::Malformed(None)
+                }
+            })
+            .collect();
+        Ok(transactions)
+    }
+}
+
+/// A json array of transaction signatures, each looked up over rpc.
+/// Authenticated with a static token in the authorization header.
+pub struct SignatureList {
+    auth_token: String,
+}
+
+impl WebhookProvider for SignatureList {
+    fn name(&self) -> &'static str {
+        "signatures"
+    }
+
+    fn authenticate(&self, req: &HttpRequest, _body: &[u8]) -> Result<(), Error> {
+        authenticate_token(req, self.auth_token.as_str())
+    }
+
+    fn parse(&self, body: &[u8]) -> Result<Vec<WebhookTransaction>, Error> {
+        let transactions = parse_entries::<String>(body)?
+            .into_iter()
+            .map(|signature| match signature {
+                Ok(signature) => match Signature::from_str(signature.as_str()) {
+                    Ok(signature) => WebhookTransaction::Signature(signature),
+                    Err(_) => {
+                        log::error!("malformed webhook signature: {}", signature);
+                        WebhookTransaction::Malformed(Some(signature))
+                    }
+                },
+                Err(err) => {
+                    log::error!("malformed webhook payload: {:?}", err);
+                    WebhookTransaction::Malformed(None)
+                }
+            })
+            .collect();
+        Ok(transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::to_bytes, http::StatusCode, test::TestRequest};
+
+    use super::*;
+
+    fn hmac_provider() -> HmacSigned {
+        HmacSigned {
+            secret: b"secret".to_vec(),
+            header: "X-Signature".to_string(),
+        }
+    }
+
+    fn sign(body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn signed_request(signature: &str) -> HttpRequest {
+        TestRequest::default()
+            .insert_header(("X-Signature", signature))
+            .to_http_request()
+    }
+
+    #[test]
+    fn hmac_accepts_the_signature_with_or_without_prefix() {
+        let provider = hmac_provider();
+        let body = b"[]";
+        let signature = sign(body);
+        assert!(provider
+            .authenticate(&signed_request(signature.as_str()), body)
+            .is_ok());
+        assert!(provider
+            .authenticate(
+                &signed_request(format!("sha256={}", signature).as_str()),
+                body
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn hmac_rejects_bad_signatures() {
+        let provider = hmac_provider();
+        let body = b"[]";
+        let signature = sign(body);
+
+        // signed over another body
+        assert!(provider
+            .authenticate(&signed_request(signature.as_str()), b"[1]")
+            .is_err());
+        // not hex
+        assert!(provider
+            .authenticate(&signed_request("sha256=zz"), body)
+            .is_err());
+        // another scheme
+        assert!(provider
+            .authenticate(
+                &signed_request(format!("sha1={}", signature).as_str()),
+                body
+            )
+            .is_err());
+        // missing header
+        assert!(provider
+            .authenticate(&TestRequest::default().to_http_request(), body)
+            .is_err());
+    }
+
+    #[test]
+    fn token_auth_compares_the_authorization_header() {
+        let req = |token: &str| {
+            TestRequest::default()
+                .insert_header(("Authorization", token))
+                .to_http_request()
+        };
+        assert!(authenticate_token(&req("token"), "token").is_ok());
+        assert!(authenticate_token(&req("other"), "token").is_err());
+        assert!(authenticate_token(&TestRequest::default().to_http_request(), "token").is_err());
+    }
+
+    #[test]
+    fn parse_keeps_the_entries_around_malformed_ones() {
+        let signature = Signature::new_unique().to_string();
+        let body = serde_json::json!([
+            {
+                "signature": signature,
+                "slot": 1,
+                "blockTime": 2,
+                "logMessages": ["Program log: hello"],
+            },
+            { "signature": signature },
+            {
+                "signature": "not a signature",
+                "slot": 1,
+                "blockTime": 2,
+                "logMessages": [],
+            },
+        ]);
+        let transactions = hmac_provider().parse(body.to_string().as_bytes()).unwrap();
+        assert!(matches!(
+            transactions.as_slice(),
+            [
+                WebhookTransaction::Ignored(ignored),
+                WebhookTransaction::Malformed(None),
+                WebhookTransaction::Malformed(Some(malformed)),
+            ] if ignored.to_string() == signature && malformed == "not a signature"
+        ));
+
+        // the batch itself must be an array
+        assert!(hmac_provider().parse(b"{}").is_err());
+    }
+
+    #[test]
+    fn signature_list_parses_each_signature() {
+        let provider = SignatureList {
+            auth_token: "token".to_string(),
+        };
+        let signature = Signature::new_unique();
+        let body = serde_json::json!([signature.to_string(), "not a signature", 1]);
+        let transactions = provider.parse(body.to_string().as_bytes()).unwrap();
+        assert!(matches!(
+            transactions.as_slice(),
+            [
+                WebhookTransaction::Signature(parsed),
+                WebhookTransaction::Malformed(Some(_)),
+                WebhookTransaction::Malformed(None),
+            ] if *parsed == signature
+        ));
+    }
+
+    #[actix_web::test]
+    async fn batch_response_asks_for_a_retry_on_failures() {
+        let result = |status| PayloadResult {
+            signature: None,
+            status,
+        };
+        let response = batch_response(&[
+            result(PayloadStatus::Submitted),
+            result(PayloadStatus::Ignored),
+            result(PayloadStatus::Quarantined),
+            result(PayloadStatus::Malformed),
+        ]);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body[0]["status"], "submitted");
+        assert_eq!(body[3]["status"], "malformed");
+
+        let response = batch_response(&[
+            result(PayloadStatus::Submitted),
+            result(PayloadStatus::Failed),
+        ]);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body[1]["status"], "failed");
+
+        assert_eq!(batch_response(&[]).status(), StatusCode::OK);
+    }
 }
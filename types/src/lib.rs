@@ -8,6 +8,21 @@ pub struct RegisterPayload {
     pub authority: Pubkey,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LeavePayload {
+    /// The authority of the member account that left the pool.
+    pub authority: Pubkey,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TransferMemberPayload {
+    /// The authority the member account was transferred from.
+    pub authority: Pubkey,
+
+    /// The authority the member account was transferred to.
+    pub new_authority: Pubkey,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetMemberPayload {
     /// The authority of the member account sending the payload.
@@ -20,6 +35,12 @@ pub struct GetEventPayload {
     pub authority: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GetBanPayload {
+    /// The banned member authority or ip address.
+    pub subject: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetChallengePayload {
     /// The authority of the member account sending the payload.
@@ -47,6 +68,50 @@ pub struct ContributePayloadV2 {
     pub solution: Solution,
 }
 
+/// Why a contribution was accepted or rejected.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContributeCode {
+    /// The contribution was counted for the current challenge.
+    Accepted,
+
+    /// The digest is not a valid drillx solution for the current challenge.
+    InvalidDigest,
+
+    /// The digest solves the previous challenge, which has since been submitted.
+    StaleChallenge,
+
+    /// The difficulty of the solution is below the min difficulty of the current challenge.
+    BelowMinDifficulty,
+
+    /// The nonce is outside the range assigned to the member.
+    NonceOutOfRange,
+
+    /// The member account is not registered with the pool.
+    UnknownMember,
+
+    /// The member already contributed an equal or better score for the current challenge.
+    Duplicate,
+
+    /// The member authority or ip address sent too many contributions.
+    RateLimited,
+
+    /// The member authority or ip address is banned for repeated invalid contributions.
+    Banned,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContributeResponse {
+    /// Why the contribution was accepted or rejected.
+    pub code: ContributeCode,
+
+    /// The difficulty of the solution.
+    pub difficulty: u32,
+
+    /// The score counted for the contribution, zero if rejected.
+    pub score: u64,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UpdateBalancePayload {
     /// The authority of the member account sending the payload.
@@ -187,3 +252,197 @@ pub struct PoolMemberMiningEvent {
     pub member_difficulty: u64,
     pub member_reward: u64,
 }
+
+/// The response from the /member/{authority}/proof request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemberBalanceProof {
+    /// The authority of the member account.
+    pub authority: Pubkey,
+
+    /// The id of the member account, committed to in the balance root.
+    pub id: u64,
+
+    /// The lifetime earnings committed to in the balance root.
+    pub total_balance: u64,
+
+    /// The balance root posted on-chain.
+    pub root: [u8; 32],
+
+    /// The sibling nodes from the member leaf up to the root.
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetAttestationProofPayload {
+    /// The challenge the attestation was submitted for.
+    pub last_hash_at: i64,
+
+    /// The authority of the member account sending the payload.
+    pub authority: String,
+}
+
+/// The response from the /attestation/{last_hash_at}/proof/{authority} request.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttestationProof {
+    /// The challenge the attestation was submitted for.
+    pub last_hash_at: i64,
+
+    /// The authority of the member account.
+    pub member: Pubkey,
+
+    /// The digest of the member's contribution.
+    pub digest: [u8; 16],
+
+    /// The nonce of the member's contribution.
+    pub nonce: [u8; 8],
+
+    /// The attestation submitted on-chain.
+    pub attestation: [u8; 32],
+
+    /// The sibling nodes from the contribution leaf up to the attestation.
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// The record of a pool submission, published so anyone can recompute the attestation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Block {
+    /// The challenge the contributions were submitted for.
+    pub challenge: Challenge,
+
+    /// Every contribution counted, in the order of the attestation leaves.
+    pub contributions: Vec<BlockContribution>,
+
+    /// The best solution, submitted on-chain.
+    pub winner: BlockWinner,
+
+    /// The attestation submitted on-chain.
+    pub attestation: [u8; 32],
+
+    /// The submission transaction signature.
+    pub signature: Signature,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockContribution {
+    /// The authority of the member account.
+    pub member: Pubkey,
+
+    /// The difficulty score of the solution.
+    pub score: u64,
+
+    /// The best solution submitted by the member.
+    pub solution: Solution,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockWinner {
+    /// The winning solution.
+    pub solution: Solution,
+
+    /// The difficulty of the winning solution.
+    pub difficulty: u32,
+}
+
+/// Whether a contribution was counted towards the pool's submission.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContributionStatus {
+    Accepted,
+    Rejected,
+}
+
+/// The contribution record that sits in the operator database.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContributionRecord {
+    /// The authority of the member account.
+    pub member: Pubkey,
+
+    /// The challenge the contribution was submitted for.
+    pub last_hash_at: i64,
+
+    /// The digest of the solution.
+    pub digest: [u8; 16],
+
+    /// The nonce of the solution.
+    pub nonce: [u8; 8],
+
+    /// The difficulty of the solution.
+    pub difficulty: u32,
+
+    /// The difficulty score of the solution.
+    pub score: u64,
+
+    /// Whether or not the contribution was accepted.
+    pub status: ContributionStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetChallengeContributionsPayload {
+    /// The challenge the contributions were submitted for.
+    pub last_hash_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContributionsQuery {
+    /// The max number of records to return, most recent first.
+    pub limit: Option<i64>,
+}
+
+/// A mining event of the pool, as recorded in the operator database.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolMiningEventRecord {
+    pub signature: Signature,
+    pub block: u64,
+    pub timestamp: u64,
+    pub balance: u64,
+    pub difficulty: u64,
+    pub last_hash_at: i64,
+    pub timing: i64,
+    pub net_reward: u64,
+    pub net_base_reward: u64,
+    pub net_miner_boost_reward: u64,
+    pub net_staker_boost_reward: u64,
+
+    /// The total rewards attributed to members, including the operator commission.
+    pub total_member_rewards: u64,
+
+    /// The number of members rewarded.
+    pub total_members: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQuery {
+    /// The max number of events to return, most recent first.
+    pub limit: Option<i64>,
+
+    /// Only return events with a last hash at before this one, for paging.
+    pub before: Option<i64>,
+
+    /// Only return events with a last hash at after this one.
+    pub after: Option<i64>,
+
+    /// Only return events with a block time at or after this unix timestamp.
+    pub start_time: Option<i64>,
+
+    /// Only return events with a block time at or before this unix timestamp.
+    pub end_time: Option<i64>,
+}
+
+/// A temporary ban on an abusive contributor, as recorded in the operator database.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Ban {
+    /// The banned member authority or ip address.
+    pub subject: String,
+
+    /// The rejection that triggered the ban.
+    pub reason: String,
+
+    /// The number of times the subject has been banned.
+    pub offences: u32,
+
+    /// The unix timestamp the ban started at.
+    pub banned_at: i64,
+
+    /// The unix timestamp the ban expires at.
+    pub expires_at: i64,
+}